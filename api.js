@@ -10,79 +10,322 @@ import {
   validateBatch as validateBatchWasm,
   validateOOOBatch as validateOOOBatchWasm,
   validateMultiAuthorBatch as validateMultiAuthorBatchWasm,
+  detectForks as detectForksWasm,
+  validate as validateWasm,
+  validateBendyButtSingle as validateBendyButtSingleWasm,
+  validateBendyButtBatch as validateBendyButtBatchWasm,
+  validateBendyButtOOOBatch as validateBendyButtOOOBatchWasm,
+  validateButtwooSingle as validateButtwooSingleWasm,
+  validateButtwooBatch as validateButtwooBatchWasm,
+  validateButtwooOOOBatch as validateButtwooOOOBatchWasm,
+  validateGabbyGroveSingle as validateGabbyGroveSingleWasm,
+  validateGabbyGroveBatch as validateGabbyGroveBatchWasm,
+  validateGabbyGroveOOOBatch as validateGabbyGroveOOOBatchWasm,
+  validateBambooSingle as validateBambooSingleWasm,
+  validateBambooBatch as validateBambooBatchWasm,
+  validateMetafeedTree as validateMetafeedTreeWasm,
+  FeedState,
+  HmacKey,
+  ReorderBuffer,
 } from "./pkg/ssb_validate2_rsjs_wasm.js";
 
-// "The buffer module from node.js, for the browser"
-const Buffer = require("buffer/").Buffer;
-
 const stringify = (msg) => JSON.stringify(msg, null, 2);
 
-const toBuffer = (hmacKey) => {
-  let hmacVal;
-  let err;
-  if (!hmacKey) {
-    hmacVal = null;
-  } else {
-    hmacVal = Buffer.isBuffer(hmacKey)
-      ? hmacKey
-      : Buffer.from(hmacKey, "base64");
-    if (typeof hmacKey === "string") {
-      if (hmacVal.toString("base64") !== hmacKey)
-        err = "hmac key invalid: string must be base64 encoded";
+// messages of the binary feed formats (e.g. Bendy Butt) are passed on as base64 strings, to be
+// told apart from message values in batches of mixed feed formats
+const isBinary = (msg) => ArrayBuffer.isView(msg) || msg instanceof ArrayBuffer;
+
+const toBase64 = (msg) => {
+  const bytes = new Uint8Array(msg.buffer || msg, msg.byteOffset, msg.byteLength);
+  let binary = "";
+  for (const byte of bytes) binary += String.fromCharCode(byte);
+  return btoa(binary);
+};
+
+const stringifyAnyFormat = (msg) =>
+  isBinary(msg) ? JSON.stringify(toBase64(msg)) : stringify(msg);
+
+// buffers are passed on as base64 strings, so that every form of hmac key (a single key, an
+// array of keys or a `{ authors, default }` map) can be cached by its JSON
+const toCacheable = (hmacKey) => {
+  if (!hmacKey) return null;
+  if (isBinary(hmacKey)) return toBase64(hmacKey);
+  if (Array.isArray(hmacKey)) return hmacKey.map(toCacheable);
+  if (hmacKey.authors) {
+    const authors = {};
+    for (const [author, key] of Object.entries(hmacKey.authors))
+      authors[author] = toCacheable(key);
+    return { ...hmacKey, authors, default: toCacheable(hmacKey.default) };
+  }
+  return hmacKey;
+};
+
+// hmac keys are decoded and validated once, in Rust, and the resulting `HmacKey` is held for
+// later calls with the same key; the least recently created key is freed beyond the maximum
+const MAX_HMAC_KEYS = 16;
+const hmacKeys = new Map();
+
+const toHmacKey = (hmacKey) => {
+  const value = toCacheable(hmacKey);
+  const cacheKey = JSON.stringify(value);
+  if (!hmacKeys.has(cacheKey)) {
+    let key;
+    try {
+      key = new HmacKey(value);
+    } catch (err) {
+      return [err];
+    }
+    if (hmacKeys.size >= MAX_HMAC_KEYS) {
+      const [oldest, evicted] = hmacKeys.entries().next().value;
+      hmacKeys.delete(oldest);
+      evicted.free();
     }
+    hmacKeys.set(cacheKey, key);
   }
-  return [err, hmacVal];
+  return [null, hmacKeys.get(cacheKey)];
 };
 
 const verifySignatures = (hmacKey, msgs) => {
   if (!Array.isArray(msgs)) return "input must be an array of message objects";
   const jsonMsgs = msgs.map(stringify);
-  const [err, hmacVal] = toBuffer(hmacKey);
+  const [err, key] = toHmacKey(hmacKey);
   if (err) return [err];
-  return verifySignaturesWasm(hmacVal, jsonMsgs);
+  // when `hmacKey` is an array, `result` is an object with the `keys` and `matchedKeys`
+  return verifySignaturesWasm(key, jsonMsgs);
 };
 
 const validateSingle = (hmacKey, msg, previous) => {
   const jsonMsg = stringify(msg);
-  const [err, hmacVal] = toBuffer(hmacKey);
+  const [err, key] = toHmacKey(hmacKey);
   if (err) return [err];
   if (previous) {
     const jsonPrevious = stringify(previous);
     // `result` is a string of the hash (`key`) for the given `jsonMsg` value
-    return validateSingleWasm(hmacVal, jsonMsg, jsonPrevious);
+    return validateSingleWasm(key, jsonMsg, jsonPrevious);
   }
-  return validateSingleWasm(hmacVal, jsonMsg);
+  return validateSingleWasm(key, jsonMsg);
 };
 
+// `validateBatch`, `validateOOOBatch` and `validateMultiAuthorBatch` are `validate` with the
+// matching `ordering` and no other options
 const validateBatch = (hmacKey, msgs, previous) => {
   if (!Array.isArray(msgs)) return "input must be an array of message objects";
   const jsonMsgs = msgs.map(stringify);
-  const [err, hmacVal] = toBuffer(hmacKey);
+  const [err, key] = toHmacKey(hmacKey);
   if (err) return [err];
   if (previous) {
     const jsonPrevious = stringify(previous);
     // `result` is an array of strings (each string a `key`) for the given `jsonMsgs`
-    return validateBatchWasm(hmacVal, jsonMsgs, jsonPrevious);
+    return validateBatchWasm(key, jsonMsgs, jsonPrevious);
   }
-  return validateBatchWasm(hmacVal, jsonMsgs);
+  return validateBatchWasm(key, jsonMsgs);
 };
 
 const validateOOOBatch = (hmacKey, msgs) => {
   if (!Array.isArray(msgs)) return "input must be an array of message objects";
   const jsonMsgs = msgs.map(stringify);
-  const [err, hmacVal] = toBuffer(hmacKey);
+  const [err, key] = toHmacKey(hmacKey);
   if (err) return [err];
-  return validateOOOBatchWasm(hmacVal, jsonMsgs);
+  return validateOOOBatchWasm(key, jsonMsgs);
 };
 
 const validateMultiAuthorBatch = (hmacKey, msgs) => {
+  if (!Array.isArray(msgs)) return "input must be an array of message objects";
+  // `msgs` may mix message values with encoded messages of the binary feed formats
+  const jsonMsgs = msgs.map(stringifyAnyFormat);
+  const [err, key] = toHmacKey(hmacKey);
+  if (err) return [err];
+  // when `hmacKey` is an array, `result` is an object with the `keys` and `matchedKeys`
+  return validateMultiAuthorBatchWasm(key, jsonMsgs);
+};
+
+const validate = (msgs, options) => {
+  if (!Array.isArray(msgs)) return "input must be an array of message objects";
+  const jsonMsgs = msgs.map(stringifyAnyFormat);
+  const { hmacKey, previous, ...rest } = options || {};
+  const [err, key] = toHmacKey(hmacKey);
+  if (err) return [err];
+  // `previous` is passed on as a message value or reference string, as for `validateBatch`
+  if (previous) rest.previous = stringify(previous);
+  // `result` is an array of keys, or an object with the `keys` and the requested parts of the
+  // report
+  return validateWasm(key, jsonMsgs, rest);
+};
+
+// binary messages (e.g. Bendy Butt) are passed on as bytes rather than stringified
+const toBytes = (msg) => (msg ? new Uint8Array(msg) : undefined);
+
+const validateBendyButtSingle = (hmacKey, msg, previous) => {
+  const [err, key] = toHmacKey(hmacKey);
+  if (err) return [err];
+  // `result` is the `%...=.bbmsg-v1` key of the given `msg`
+  return validateBendyButtSingleWasm(key, toBytes(msg), toBytes(previous));
+};
+
+const validateBendyButtBatch = (hmacKey, msgs, previous) => {
+  if (!Array.isArray(msgs)) return "input must be an array of message buffers";
+  const [err, key] = toHmacKey(hmacKey);
+  if (err) return [err];
+  // `result` is an array of `%...=.bbmsg-v1` keys for the given `msgs`
+  return validateBendyButtBatchWasm(
+    key,
+    msgs.map(toBytes),
+    toBytes(previous)
+  );
+};
+
+const validateBendyButtOOOBatch = (hmacKey, msgs) => {
+  if (!Array.isArray(msgs)) return "input must be an array of message buffers";
+  const [err, key] = toHmacKey(hmacKey);
+  if (err) return [err];
+  return validateBendyButtOOOBatchWasm(key, msgs.map(toBytes));
+};
+
+const validateButtwooSingle = (hmacKey, msg, previous) => {
+  const [err, key] = toHmacKey(hmacKey);
+  if (err) return [err];
+  // `result` is the `ssb:message/buttwoo-v1/...` key of the given `msg`
+  return validateButtwooSingleWasm(key, toBytes(msg), toBytes(previous));
+};
+
+const validateButtwooBatch = (hmacKey, msgs, previous) => {
+  if (!Array.isArray(msgs)) return "input must be an array of message buffers";
+  const [err, key] = toHmacKey(hmacKey);
+  if (err) return [err];
+  // `result` is an array of `ssb:message/buttwoo-v1/...` keys for the given `msgs`
+  return validateButtwooBatchWasm(
+    key,
+    msgs.map(toBytes),
+    toBytes(previous)
+  );
+};
+
+const validateButtwooOOOBatch = (hmacKey, msgs) => {
+  if (!Array.isArray(msgs)) return "input must be an array of message buffers";
+  const [err, key] = toHmacKey(hmacKey);
+  if (err) return [err];
+  return validateButtwooOOOBatchWasm(key, msgs.map(toBytes));
+};
+
+const validateGabbyGroveSingle = (hmacKey, msg, previous) => {
+  const [err, key] = toHmacKey(hmacKey);
+  if (err) return [err];
+  // `result` is the `%...=.ggmsg-v1` key of the given `msg`
+  return validateGabbyGroveSingleWasm(key, toBytes(msg), toBytes(previous));
+};
+
+const validateGabbyGroveBatch = (hmacKey, msgs, previous) => {
+  if (!Array.isArray(msgs)) return "input must be an array of message buffers";
+  const [err, key] = toHmacKey(hmacKey);
+  if (err) return [err];
+  // `result` is an array of `%...=.ggmsg-v1` keys for the given `msgs`
+  return validateGabbyGroveBatchWasm(
+    key,
+    msgs.map(toBytes),
+    toBytes(previous)
+  );
+};
+
+const validateGabbyGroveOOOBatch = (hmacKey, msgs) => {
+  if (!Array.isArray(msgs)) return "input must be an array of message buffers";
+  const [err, key] = toHmacKey(hmacKey);
+  if (err) return [err];
+  return validateGabbyGroveOOOBatchWasm(key, msgs.map(toBytes));
+};
+
+// Bamboo is not a Scuttlebutt format, so no hmac key is taken
+const validateBambooSingle = (entry, payload, lipmaaEntry, backlinkEntry) => {
+  // `result` is the hex encoded YAMF hash of the given `entry`
+  return validateBambooSingleWasm(
+    toBytes(entry),
+    toBytes(payload),
+    toBytes(lipmaaEntry),
+    toBytes(backlinkEntry)
+  );
+};
+
+const validateBambooBatch = (entries, payloads) => {
+  if (!Array.isArray(entries)) return "input must be an array of entry buffers";
+  // `payloads` holds the payload of each entry (or `null`) at the same position
+  const payloadBytes = payloads
+    ? payloads.map((payload) => toBytes(payload) || null)
+    : null;
+  // `result` is an object with the `keys` of the `entries` and whether each is `anchored`
+  return validateBambooBatchWasm(entries.map(toBytes), payloadBytes);
+};
+
+const validateMetafeedTree = (hmacKey, metafeedMsgs, mainFeedMsgs) => {
+  if (!Array.isArray(metafeedMsgs))
+    return "input must be an array of message buffers";
+  if (!Array.isArray(mainFeedMsgs))
+    return "input must be an array of message objects";
+  const [err, key] = toHmacKey(hmacKey);
+  if (err) return [err];
+  // `result` is an object with the `roots` of the metafeed trees and any `inconsistencies`
+  return validateMetafeedTreeWasm(
+    key,
+    metafeedMsgs.map(toBytes),
+    mainFeedMsgs.map(stringify)
+  );
+};
+
+const detectForks = (hmacKey, msgs, knownTips) => {
+  if (!Array.isArray(msgs)) return "input must be an array of message objects";
+  const jsonMsgs = msgs.map(stringify);
+  const [err, key] = toHmacKey(hmacKey);
+  if (err) return [err];
+  // `result` is an array of `{ author, sequence, keys }` objects, one for each fork
+  return detectForksWasm(key, jsonMsgs, knownTips || null);
+};
+
+// per-author validation state, held for the lifetime of the worker
+let feedState = null;
+
+const getFeedState = () => {
+  if (!feedState) feedState = new FeedState();
+  return feedState;
+};
+
+const validateWithState = (hmacKey, msgs) => {
+  if (!Array.isArray(msgs)) return "input must be an array of message objects";
+  const jsonMsgs = msgs.map(stringify);
+  const [err, key] = toHmacKey(hmacKey);
+  if (err) return [err];
+  return getFeedState().validate(key, jsonMsgs);
+};
+
+// `result` is a `Uint8Array` snapshot of the per-author validation state
+const exportState = () => [null, getFeedState().toSnapshot()];
+
+// `result` is the number of feeds restored from the snapshot
+const importState = (snapshot) => getFeedState().loadSnapshot(snapshot);
+
+// buffer for out-of-order messages, held for the lifetime of the worker
+let reorderBuffer = null;
+
+const getReorderBuffer = () => {
+  if (!reorderBuffer) reorderBuffer = new ReorderBuffer();
+  return reorderBuffer;
+};
+
+const validateBuffered = (hmacKey, msgs) => {
   if (!Array.isArray(msgs)) return "input must be an array of message objects";
   const jsonMsgs = msgs.map(stringify);
-  const [err, hmacVal] = toBuffer(hmacKey);
+  const [err, key] = toHmacKey(hmacKey);
   if (err) return [err];
-  return validateMultiAuthorBatchWasm(hmacVal, jsonMsgs);
+  // `result` is an object with the `validated` keys, the still `pending` messages and those
+  // `evicted` or found `invalid`
+  return getReorderBuffer().push(key, jsonMsgs);
 };
 
+// `result` is an array of `{ author, sequence }` for messages awaiting their predecessor
+const bufferPending = () => [null, getReorderBuffer().pending()];
+
+// takes the feed tips of the state of `validateWithState`; `result` is as for `validateBuffered`,
+// with the keys of the messages released from those tips
+const seedBufferFromState = () =>
+  getReorderBuffer().seedFromState(getFeedState());
+
 /*
  * Initialize the WASM module and WebWorkers.
  * The WebWorkers are used as threads for parallel validation.
@@ -99,5 +342,25 @@ export {
   validateBatch,
   validateOOOBatch,
   validateMultiAuthorBatch,
+  validate,
+  validateBendyButtSingle,
+  validateBendyButtBatch,
+  validateBendyButtOOOBatch,
+  validateButtwooSingle,
+  validateButtwooBatch,
+  validateButtwooOOOBatch,
+  validateGabbyGroveSingle,
+  validateGabbyGroveBatch,
+  validateGabbyGroveOOOBatch,
+  validateBambooSingle,
+  validateBambooBatch,
+  validateMetafeedTree,
+  detectForks,
+  validateWithState,
+  exportState,
+  importState,
+  validateBuffered,
+  bufferPending,
+  seedBufferFromState,
   ready,
 };
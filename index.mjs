@@ -43,3 +43,112 @@ export function validateOOOBatch(hmacKey, msgs, cb) {
 export function validateMultiAuthorBatch(hmacKey, msgs, cb) {
   wrapped.validateMultiAuthorBatch(hmacKey, msgs).then(convertResults(cb));
 }
+
+export function validate(msgs, options, cb) {
+  wrapped.validate(msgs, options).then(convertResults(cb));
+}
+
+export function validateBendyButtSingle(hmacKey, msg, previous, cb) {
+  wrapped
+    .validateBendyButtSingle(hmacKey, msg, previous || null)
+    .then(convertResults(cb));
+}
+
+export function validateBendyButtBatch(hmacKey, msgs, previous, cb) {
+  wrapped
+    .validateBendyButtBatch(hmacKey, msgs, previous || null)
+    .then(convertResults(cb));
+}
+
+export function validateBendyButtOOOBatch(hmacKey, msgs, cb) {
+  wrapped.validateBendyButtOOOBatch(hmacKey, msgs).then(convertResults(cb));
+}
+
+export function validateButtwooSingle(hmacKey, msg, previous, cb) {
+  wrapped
+    .validateButtwooSingle(hmacKey, msg, previous || null)
+    .then(convertResults(cb));
+}
+
+export function validateButtwooBatch(hmacKey, msgs, previous, cb) {
+  wrapped
+    .validateButtwooBatch(hmacKey, msgs, previous || null)
+    .then(convertResults(cb));
+}
+
+export function validateButtwooOOOBatch(hmacKey, msgs, cb) {
+  wrapped.validateButtwooOOOBatch(hmacKey, msgs).then(convertResults(cb));
+}
+
+export function validateGabbyGroveSingle(hmacKey, msg, previous, cb) {
+  wrapped
+    .validateGabbyGroveSingle(hmacKey, msg, previous || null)
+    .then(convertResults(cb));
+}
+
+export function validateGabbyGroveBatch(hmacKey, msgs, previous, cb) {
+  wrapped
+    .validateGabbyGroveBatch(hmacKey, msgs, previous || null)
+    .then(convertResults(cb));
+}
+
+export function validateGabbyGroveOOOBatch(hmacKey, msgs, cb) {
+  wrapped.validateGabbyGroveOOOBatch(hmacKey, msgs).then(convertResults(cb));
+}
+
+export function validateBambooSingle(
+  entry,
+  payload,
+  lipmaaEntry,
+  backlinkEntry,
+  cb
+) {
+  wrapped
+    .validateBambooSingle(
+      entry,
+      payload || null,
+      lipmaaEntry || null,
+      backlinkEntry || null
+    )
+    .then(convertResults(cb));
+}
+
+export function validateBambooBatch(entries, payloads, cb) {
+  wrapped
+    .validateBambooBatch(entries, payloads || null)
+    .then(convertResults(cb));
+}
+
+export function validateMetafeedTree(hmacKey, metafeedMsgs, mainFeedMsgs, cb) {
+  wrapped
+    .validateMetafeedTree(hmacKey, metafeedMsgs, mainFeedMsgs)
+    .then(convertResults(cb));
+}
+
+export function detectForks(hmacKey, msgs, knownTips, cb) {
+  wrapped.detectForks(hmacKey, msgs, knownTips).then(convertResults(cb));
+}
+
+export function validateWithState(hmacKey, msgs, cb) {
+  wrapped.validateWithState(hmacKey, msgs).then(convertResults(cb));
+}
+
+export function exportState(cb) {
+  wrapped.exportState().then(convertResults(cb));
+}
+
+export function importState(snapshot, cb) {
+  wrapped.importState(snapshot).then(convertResults(cb));
+}
+
+export function validateBuffered(hmacKey, msgs, cb) {
+  wrapped.validateBuffered(hmacKey, msgs).then(convertResults(cb));
+}
+
+export function bufferPending(cb) {
+  wrapped.bufferPending().then(convertResults(cb));
+}
+
+export function seedBufferFromState(cb) {
+  wrapped.seedBufferFromState().then(convertResults(cb));
+}
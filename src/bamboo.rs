@@ -0,0 +1,383 @@
+// SPDX-FileCopyrightText: 2021 Andrew 'glyph' Reid
+//
+// SPDX-License-Identifier: LGPL-3.0-only
+
+//! Validation of Bamboo log entries: an append-only log format whose entries link both to the
+//! entry before them (the backlink) and to an earlier entry chosen so that any entry can be
+//! reached from the first in a logarithmic number of steps (the lipmaa link).
+//!
+//! An entry is encoded as `isEndOfFeed` (one byte), `author` (a 32 byte ed25519 public key),
+//! `logId` and `seqNum` (varu64), then for entries after the first the `lipmaaLink` (only when
+//! it differs from the backlink) and the `backlink`, followed by `payloadSize` (varu64),
+//! `payloadHash` and the 64 byte signature over everything before it. Hashes are YAMF encoded
+//! BLAKE2b-512 hashes; the hash of an entry is that of its full encoding.
+//!
+//! Entries do not need to form a contiguous run: given a pool of entries of one log, each link
+//! to an entry in the pool is checked, and an entry is anchored when the pool holds its
+//! certificate pool (every entry on its lipmaa path back to the first entry).
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::crypto;
+
+/// YAMF type of a BLAKE2b hash.
+const YAMF_BLAKE2B: u64 = 0;
+/// Byte length of a BLAKE2b-512 hash.
+const BLAKE2B_BYTES: u64 = 64;
+/// The largest sequence number for which the lipmaa link can be computed without overflow,
+/// `(3^40 - 1) / 2` (the next power of three does not fit in a `u64`).
+const MAX_SEQ_NUM: u64 = 6_078_832_729_528_464_400;
+
+/// The fields of a verified Bamboo entry.
+pub struct BambooEntry {
+    pub is_end_of_feed: bool,
+    /// Public key of the author.
+    pub author: Vec<u8>,
+    pub log_id: u64,
+    pub seq_num: u64,
+    /// YAMF hash of the lipmaa entry (`None` for the first entry).
+    pub lipmaa_link: Option<Vec<u8>>,
+    /// YAMF hash of the previous entry (`None` for the first entry).
+    pub backlink: Option<Vec<u8>>,
+    /// YAMF hash of the entry.
+    pub hash: Vec<u8>,
+}
+
+impl BambooEntry {
+    /// The hex encoded YAMF hash of the entry.
+    pub fn key(&self) -> String {
+        to_hex(&self.hash)
+    }
+}
+
+/// Result of validating a pool of entries of one log.
+#[derive(Debug, Serialize)]
+pub struct BambooReport {
+    /// Hex encoded YAMF hash of each entry.
+    pub keys: Vec<String>,
+    /// Whether the certificate pool of each entry is present and verified.
+    pub anchored: Vec<bool>,
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// The YAMF encoding of the BLAKE2b-512 hash of `data`.
+fn yamf_hash(data: &[u8]) -> Vec<u8> {
+    let mut hash = vec![YAMF_BLAKE2B as u8, BLAKE2B_BYTES as u8];
+    hash.extend_from_slice(&crypto::blake2b(data));
+    hash
+}
+
+/// The sequence number of the entry which the lipmaa link of entry `n` points to. `n` must be
+/// at most `MAX_SEQ_NUM`, as checked when an entry is decoded.
+pub fn lipmaa(n: u64) -> u64 {
+    let mut m: u64 = 1;
+    let mut po3: u64 = 3;
+    let mut u = n;
+
+    // find the smallest k such that (3^k - 1) / 2 >= n
+    while m < n {
+        po3 *= 3;
+        m = (po3 - 1) / 2;
+    }
+
+    // then the longest jump back which keeps the path logarithmic
+    po3 /= 3;
+    if m != n {
+        while u != 0 {
+            m = (po3 - 1) / 2;
+            po3 /= 3;
+            u %= m;
+        }
+        if m != po3 {
+            po3 = m;
+        }
+    }
+    n - po3
+}
+
+struct Reader<'a> {
+    input: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize, name: &str) -> Result<&'a [u8], String> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|end| *end <= self.input.len())
+            .ok_or_else(|| format!("entry invalid: {} exceeds input", name))?;
+        let bytes = &self.input[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    /// Read a canonically encoded varu64.
+    fn read_varu64(&mut self, name: &str) -> Result<u64, String> {
+        let first = self.take(1, name)?[0];
+        if first < 248 {
+            return Ok(u64::from(first));
+        }
+        let len = usize::from(first - 247);
+        let value = self
+            .take(len, name)?
+            .iter()
+            .fold(0, |value, byte| value << 8 | u64::from(*byte));
+        let min = if len == 1 { 248 } else { 1 << (8 * (len - 1)) };
+        if value < min {
+            return Err(format!(
+                "entry invalid: {} is not canonically encoded",
+                name
+            ));
+        }
+        Ok(value)
+    }
+
+    fn read_yamf_hash(&mut self, name: &str) -> Result<Vec<u8>, String> {
+        let start = self.pos;
+        if self.read_varu64(name)? != YAMF_BLAKE2B || self.read_varu64(name)? != BLAKE2B_BYTES {
+            return Err(format!("{} invalid: must be a BLAKE2b-512 hash", name));
+        }
+        self.take(BLAKE2B_BYTES as usize, name)?;
+        Ok(self.input[start..self.pos].to_vec())
+    }
+}
+
+/// Decode an entry and verify its signature, along with the size and hash of the payload when
+/// given, without reference to any other entry of the log.
+pub fn verify(entry: &[u8], payload: Option<&[u8]>) -> Result<BambooEntry, String> {
+    let mut reader = Reader {
+        input: entry,
+        pos: 0,
+    };
+    let is_end_of_feed = match reader.take(1, "end of feed flag")? {
+        [0] => false,
+        [1] => true,
+        _ => return Err("entry invalid: malformed end of feed flag".to_string()),
+    };
+    let author = reader.take(32, "author")?.to_vec();
+    let log_id = reader.read_varu64("log id")?;
+    let seq_num = reader.read_varu64("sequence number")?;
+    if seq_num == 0 {
+        return Err("entry invalid: sequence number must be at least 1".to_string());
+    }
+    if seq_num > MAX_SEQ_NUM {
+        return Err(format!(
+            "entry invalid: sequence number must be at most {}",
+            MAX_SEQ_NUM
+        ));
+    }
+    let (lipmaa_link, backlink) = if seq_num > 1 {
+        let lipmaa_link = if lipmaa(seq_num) != seq_num - 1 {
+            Some(reader.read_yamf_hash("lipmaa link")?)
+        } else {
+            None
+        };
+        let backlink = reader.read_yamf_hash("backlink")?;
+        (
+            Some(lipmaa_link.unwrap_or_else(|| backlink.clone())),
+            Some(backlink),
+        )
+    } else {
+        (None, None)
+    };
+    let payload_size = reader.read_varu64("payload size")?;
+    let payload_hash = reader.read_yamf_hash("payload hash")?;
+    let signed_len = reader.pos;
+    let signature = reader.take(64, "signature")?;
+    if reader.pos != entry.len() {
+        return Err("entry invalid: trailing bytes after signature".to_string());
+    }
+
+    crypto::verify_detached(&author, signature, &entry[..signed_len], None)?;
+
+    if let Some(payload) = payload {
+        if payload.len() as u64 != payload_size {
+            return Err(format!(
+                "payload invalid: byte length ({}) does not match payload size ({})",
+                payload.len(),
+                payload_size
+            ));
+        }
+        if yamf_hash(payload) != payload_hash {
+            return Err("payload invalid: does not match payload hash".to_string());
+        }
+    }
+
+    Ok(BambooEntry {
+        is_end_of_feed,
+        author,
+        log_id,
+        seq_num,
+        lipmaa_link,
+        backlink,
+        hash: yamf_hash(entry),
+    })
+}
+
+/// Check a link of `entry` (its lipmaa link or backlink, as named) to the entry it points to.
+fn validate_link(
+    entry: &BambooEntry,
+    link: Option<&[u8]>,
+    target: &BambooEntry,
+    name: &str,
+) -> Result<(), String> {
+    if entry.author != target.author || entry.log_id != target.log_id {
+        return Err(format!(
+            "{} entry is not of the same log as the entry",
+            name
+        ));
+    }
+    if target.is_end_of_feed {
+        return Err(format!("{} entry ended the log", name));
+    }
+    if link != Some(&target.hash[..]) {
+        return Err(format!(
+            "{} of entry did not match hash of {} entry ({})",
+            name,
+            name,
+            target.key()
+        ));
+    }
+    Ok(())
+}
+
+/// Check the links of a verified entry to its lipmaa and previous entries, when given.
+pub fn validate_links(
+    entry: &BambooEntry,
+    lipmaa_entry: Option<&BambooEntry>,
+    backlink_entry: Option<&BambooEntry>,
+) -> Result<(), String> {
+    if let Some(lipmaa_entry) = lipmaa_entry {
+        if lipmaa_entry.seq_num != lipmaa(entry.seq_num) {
+            return Err(format!(
+                "sequence number of lipmaa entry ({}) must be {}",
+                lipmaa_entry.seq_num,
+                lipmaa(entry.seq_num)
+            ));
+        }
+        validate_link(
+            entry,
+            entry.lipmaa_link.as_deref(),
+            lipmaa_entry,
+            "lipmaa link",
+        )?;
+    }
+    if let Some(backlink_entry) = backlink_entry {
+        if backlink_entry.seq_num + 1 != entry.seq_num {
+            return Err(format!(
+                "sequence number of entry ({}) is not one greater than that of backlink entry ({})",
+                entry.seq_num, backlink_entry.seq_num
+            ));
+        }
+        validate_link(entry, entry.backlink.as_deref(), backlink_entry, "backlink")?;
+    }
+    Ok(())
+}
+
+/// Format the error for an invalid entry, which is included hex encoded.
+pub fn invalid(e: impl std::fmt::Display, entry: &[u8]) -> String {
+    format!("found invalid entry: {}: {}", e, to_hex(entry))
+}
+
+/// Verify an entry and the given entries it links to. Returns the key of the entry.
+pub fn validate_single(
+    entry: &[u8],
+    payload: Option<&[u8]>,
+    lipmaa_entry: Option<&[u8]>,
+    backlink_entry: Option<&[u8]>,
+) -> Result<String, String> {
+    let verify_linked = |linked: Option<&[u8]>| match linked {
+        Some(linked) => verify(linked, None)
+            .map(Some)
+            .map_err(|e| invalid(e, linked)),
+        None => Ok(None),
+    };
+    let lipmaa_entry = verify_linked(lipmaa_entry)?;
+    let backlink_entry = verify_linked(backlink_entry)?;
+    let verified = verify(entry, payload).map_err(|e| invalid(e, entry))?;
+    validate_links(&verified, lipmaa_entry.as_ref(), backlink_entry.as_ref())
+        .map_err(|e| invalid(e, entry))?;
+    Ok(verified.key())
+}
+
+/// Verify a pool of entries of a single log, in any order and with any gaps, along with the
+/// payload of each entry where given. Every link between two entries of the pool is checked,
+/// and each entry is reported as anchored when its certificate pool is present.
+pub fn validate_batch(
+    entries: &[Vec<u8>],
+    payloads: Option<&[Option<Vec<u8>>]>,
+) -> Result<BambooReport, String> {
+    if let Some(payloads) = payloads {
+        if payloads.len() != entries.len() {
+            return Err(format!(
+                "payloads invalid: {} payloads given for {} entries",
+                payloads.len(),
+                entries.len()
+            ));
+        }
+    }
+    let verified = entries
+        .iter()
+        .enumerate()
+        .map(|(idx, entry)| {
+            let payload = payloads.and_then(|payloads| payloads[idx].as_deref());
+            verify(entry, payload).map_err(|e| invalid(e, entry))
+        })
+        .collect::<Result<Vec<BambooEntry>, String>>()?;
+
+    // index the pool by sequence number
+    let mut pool: BTreeMap<u64, usize> = BTreeMap::new();
+    for (idx, entry) in verified.iter().enumerate() {
+        if entry.author != verified[0].author || entry.log_id != verified[0].log_id {
+            return Err(invalid(
+                "entry is not of the same log as the first entry",
+                &entries[idx],
+            ));
+        }
+        if let Some(other) = pool.insert(entry.seq_num, idx) {
+            if verified[other].hash != entry.hash {
+                return Err(invalid(
+                    format!("fork detected at sequence number {}", entry.seq_num),
+                    &entries[idx],
+                ));
+            }
+        }
+    }
+    if let Some((end, _)) = pool.iter().find(|(_, idx)| verified[**idx].is_end_of_feed) {
+        if let Some((_, idx)) = pool.range(end + 1..).next() {
+            return Err(invalid(
+                format!("entry follows the end of the log at {}", end),
+                &entries[*idx],
+            ));
+        }
+    }
+
+    let mut anchored_seqs: BTreeMap<u64, bool> = BTreeMap::new();
+    for (seq_num, idx) in &pool {
+        let entry = &verified[*idx];
+        let linked = |seq_num: u64| pool.get(&seq_num).map(|idx| &verified[*idx]);
+        let (lipmaa_entry, backlink_entry) = if *seq_num > 1 {
+            (linked(lipmaa(*seq_num)), linked(seq_num - 1))
+        } else {
+            (None, None)
+        };
+        validate_links(entry, lipmaa_entry, backlink_entry)
+            .map_err(|e| invalid(e, &entries[*idx]))?;
+        let anchored = *seq_num == 1 || lipmaa_entry.is_some() && anchored_seqs[&lipmaa(*seq_num)];
+        anchored_seqs.insert(*seq_num, anchored);
+    }
+
+    Ok(BambooReport {
+        keys: verified.iter().map(BambooEntry::key).collect(),
+        anchored: verified
+            .iter()
+            .map(|entry| anchored_seqs[&entry.seq_num])
+            .collect(),
+    })
+}
@@ -0,0 +1,689 @@
+// SPDX-FileCopyrightText: 2021 Andrew 'glyph' Reid
+//
+// SPDX-License-Identifier: LGPL-3.0-only
+
+//! Validation pipelines shared by the entry points, each driven by a set of `ValidationOptions`.
+
+use ssb_validate::message_value::{
+    par_validate_message_value, par_validate_message_value_hash_chain_of_feed,
+    par_validate_ooo_message_value_hash_chain_of_feed, validate_message_value,
+    validate_message_value_hash_chain, validate_ooo_message_value_hash_chain,
+};
+
+use crate::{
+    chain, dedupe,
+    fork::{self, Fork, ForkPolicy},
+    format::{self, FeedMsg},
+    gaps,
+    hmac::HmacKeys,
+    message,
+    options::{Ordering, ValidationOptions},
+    report::{BatchReport, InvalidMessage},
+    rules::{self, Rules},
+    state::{self, FeedTip, Previous},
+    timestamp,
+};
+
+/// The report of a validated batch, along with whether any part of the report beyond the keys
+/// was requested.
+pub type BatchResult = Result<(BatchReport, bool), String>;
+
+fn invalid(e: impl std::fmt::Display, msg: &[u8]) -> String {
+    format!("found invalid message: {}: {}", e, message::as_str(msg))
+}
+
+/// Look for forks in a batch of messages (or against a known feed tip) as the fork policy
+/// requires, returning the forks found when they are to be reported.
+fn check_forks(
+    msgs: &[Vec<u8>],
+    tips: &[FeedTip],
+    options: &ValidationOptions,
+) -> Result<Option<Vec<Fork>>, String> {
+    match options.forks() {
+        ForkPolicy::Ignore => Ok(None),
+        policy => apply_fork_policy(fork::detect_forks(msgs, tips)?, policy),
+    }
+}
+
+/// Fail on the first of the forks, or return them to be reported.
+fn apply_fork_policy(forks: Vec<Fork>, policy: ForkPolicy) -> Result<Option<Vec<Fork>>, String> {
+    match (policy, forks.first()) {
+        (ForkPolicy::Error, Some(fork)) => Err(fork.to_string()),
+        (ForkPolicy::Report, _) => Ok(Some(forks)),
+        _ => Ok(None),
+    }
+}
+
+/// Convert a batch to bytes, its limits having been enforced when it was read (see
+/// `limits::read_messages`).
+fn into_batch(elements: Vec<String>) -> Vec<Vec<u8>> {
+    elements.into_iter().map(String::into_bytes).collect()
+}
+
+/// Verify the signature of each message, returning the index of the key each verified with.
+fn verify_all(msgs: &[Vec<u8>], hmac_keys: &HmacKeys) -> Result<Vec<usize>, String> {
+    // we're not running parallel verification here due to rayon issues for wasm:
+    // a dependency uses older versions of `rand` and `getrandom`, which fail to provide
+    // `thread_rng` when parallel verification is attempted in the browser.
+    msgs.iter().map(|msg| hmac_keys.verify(msg)).collect()
+}
+
+/// Verify the signature of every message unless the `signature` rule is disabled, returning the
+/// index of the key each message verified with.
+fn verify_unless_skipped(
+    msgs: &[Vec<u8>],
+    hmac_keys: &HmacKeys,
+    options: &ValidationOptions,
+) -> Result<Option<Vec<usize>>, String> {
+    if matches!(
+        options.rules,
+        Some(Rules {
+            signature: false,
+            ..
+        })
+    ) {
+        Ok(None)
+    } else {
+        verify_all(msgs, hmac_keys).map(Some)
+    }
+}
+
+/// Apply the timestamp rules (if any), collecting the warnings of warning-only rules. The first
+/// message of an ordered batch is compared with the previous message, unless only a reference to
+/// it was given.
+fn check_timestamps(
+    msgs: &[Vec<u8>],
+    options: &ValidationOptions,
+) -> Result<Option<Vec<String>>, String> {
+    let policy = match &options.timestamp_policy {
+        Some(policy) => policy,
+        None => return Ok(None),
+    };
+    let previous = match options
+        .previous
+        .as_ref()
+        .map(|msg| Previous::parse(msg.clone().into_bytes()))
+    {
+        Some(Previous::Message(msg)) => Some(msg),
+        _ => None,
+    };
+    timestamp::check_timestamps(msgs, previous.as_deref(), policy)
+}
+
+/// Validate a message trusted as a checkpoint, without reference to its predecessor. The first
+/// message of a feed is still checked in full.
+pub fn validate_checkpoint(msg: &[u8]) -> Result<(), String> {
+    let meta = message::parse_meta(msg)?;
+    let result = if meta.sequence == 1 {
+        validate_message_value_hash_chain::<_, &[u8]>(msg, None)
+    } else {
+        validate_ooo_message_value_hash_chain::<_, &[u8]>(msg, None)
+    };
+    result.map_err(|e| e.to_string())
+}
+
+/// Build the report for a validated batch, holding either a key for every position of the batch
+/// or (if `dedupe` is set) the unique keys along with the positions of the duplicates.
+fn batch_report(deduplicated: dedupe::Deduplicated, dedupe: bool) -> BatchReport {
+    if dedupe {
+        BatchReport {
+            keys: deduplicated.keys,
+            duplicates: Some(deduplicated.duplicates),
+            ..BatchReport::default()
+        }
+    } else {
+        BatchReport {
+            keys: deduplicated.keys_by_position(),
+            ..BatchReport::default()
+        }
+    }
+}
+
+/// Line the matched keys of the unique messages up with the keys of the report (`None` unless a
+/// list of HMAC keys was given).
+fn matched_keys_by_position(
+    matched_keys: Vec<usize>,
+    deduplicated: &dedupe::Deduplicated,
+    dedupe: bool,
+    hmac_keys: &HmacKeys,
+) -> Option<Vec<usize>> {
+    if !hmac_keys.is_list() {
+        None
+    } else if dedupe {
+        Some(matched_keys)
+    } else {
+        Some(
+            deduplicated
+                .positions
+                .iter()
+                .map(|idx| matched_keys[*idx])
+                .collect(),
+        )
+    }
+}
+
+/// Verify and validate an ordered run of a single feed (see `validateBatch`).
+pub fn validate_ordered(
+    hmac_keys: &HmacKeys,
+    elements: Vec<String>,
+    options: &ValidationOptions,
+) -> BatchResult {
+    if let Some(rules) = options.rules.filter(|rules| !rules.full_validation()) {
+        return validate_ordered_rules(hmac_keys, elements, options, &rules);
+    }
+
+    let mut msgs = into_batch(elements);
+
+    // remember the original position of each message so that keys can be returned in order
+    let mut positions = None;
+    if options.auto_sort {
+        let (sorted, original_positions) = chain::sort_by_sequence(msgs)?;
+        msgs = sorted;
+        positions = Some(original_positions);
+    }
+
+    let checkpoint = options.checkpoint;
+    let previous = options
+        .previous
+        .clone()
+        .map(|msg| Previous::parse(msg.into_bytes()));
+    // the previous message (if any) is the known tip of the feed
+    let previous_tip: Vec<FeedTip> = previous
+        .as_ref()
+        .and_then(|previous| previous.tip().ok())
+        .into_iter()
+        .collect();
+    let (previous_msg, previous_ref) = match previous {
+        Some(Previous::Message(msg)) => (Some(msg), None),
+        Some(Previous::Tip(tip)) => (None, Some(tip)),
+        None => (None, None),
+    };
+
+    let matched_keys = verify_unless_skipped(&msgs, hmac_keys, options)?;
+
+    // detect forks before validation, which would otherwise fail with a less specific error
+    let forks = check_forks(&msgs, &previous_tip, options)?;
+
+    let warnings = check_timestamps(&msgs, options)?;
+
+    // a previous reference is checked against the first message, which then acts as the
+    // previous message for the rest of the batch
+    let (chain, chain_previous) = match (&previous_ref, msgs.split_first()) {
+        (Some(tip), Some((first, rest))) => {
+            state::validate_against_tip(first, tip).map_err(|e| invalid(e, first))?;
+            (rest, Some(first))
+        }
+        // a trusted checkpoint is validated on its own and anchors the rest of the batch
+        (None, Some((first, rest))) if checkpoint => {
+            validate_checkpoint(first).map_err(|e| invalid(e, first))?;
+            (rest, Some(first))
+        }
+        _ => (&msgs[..], previous_msg.as_ref()),
+    };
+    let anchored_at = if checkpoint {
+        msgs.first()
+            .and_then(|msg| message::parse_meta(msg).ok())
+            .map(|meta| meta.sequence)
+    } else {
+        None
+    };
+
+    // attempt batch validation and match on error to find invalid message
+    if let Err(e) = par_validate_message_value_hash_chain_of_feed(chain, chain_previous) {
+        let invalid_msg = chain
+            .iter()
+            .find(|msg| validate_message_value_hash_chain(msg, chain_previous).is_err());
+        return Err(match invalid_msg {
+            Some(msg) => invalid(e, msg),
+            None => format!(
+                "found invalid message: {}: parallel validation failed but no single invalid message was found",
+                e
+            ),
+        });
+    }
+
+    let mut keys = crate::hash(msgs);
+    let mut matched_keys = matched_keys.filter(|_| hmac_keys.is_list());
+    if let Some(positions) = positions {
+        keys = chain::restore_order(keys, &positions);
+        matched_keys =
+            matched_keys.map(|matched_keys| chain::restore_order(matched_keys, &positions));
+    }
+    let detailed = checkpoint || forks.is_some() || warnings.is_some() || matched_keys.is_some();
+    let report = BatchReport {
+        keys,
+        anchored_at,
+        matched_keys,
+        forks,
+        warnings,
+        ..BatchReport::default()
+    };
+    Ok((report, detailed))
+}
+
+/// Validate an ordered run of a single feed with some of the rules disabled: each message value
+/// is checked as by `ssb_validate` (see `rules::check_value`), and its link to its predecessor
+/// by the enabled `author`, `sequence` and `link` rules.
+fn validate_ordered_rules(
+    hmac_keys: &HmacKeys,
+    elements: Vec<String>,
+    options: &ValidationOptions,
+    rules: &Rules,
+) -> BatchResult {
+    let mut msgs = into_batch(elements);
+
+    let mut positions = None;
+    if options.auto_sort {
+        let (sorted, original_positions) = chain::sort_by_sequence(msgs)?;
+        msgs = sorted;
+        positions = Some(original_positions);
+    }
+
+    let mut tip = match &options.previous {
+        Some(previous) => Some(Previous::parse(previous.clone().into_bytes()).tip()?),
+        None => None,
+    };
+
+    let matched_keys =
+        verify_unless_skipped(&msgs, hmac_keys, options)?.filter(|_| hmac_keys.is_list());
+
+    let previous_tip: Vec<FeedTip> = tip.iter().cloned().collect();
+    let forks = check_forks(&msgs, &previous_tip, options)?;
+
+    let warnings = check_timestamps(&msgs, options)?;
+
+    for (idx, msg) in msgs.iter().enumerate() {
+        rules::check_value(msg, rules).map_err(|e| invalid(e, msg))?;
+        // a trusted checkpoint has no predecessor to be checked against
+        let new_tip = if idx == 0 && options.checkpoint {
+            FeedTip::from_message(msg)
+        } else {
+            rules::check_link(msg, tip.as_ref(), rules)
+        };
+        tip = Some(new_tip.map_err(|e| invalid(e, msg))?);
+    }
+
+    let anchored_at = if options.checkpoint {
+        msgs.first()
+            .and_then(|msg| message::parse_meta(msg).ok())
+            .map(|meta| meta.sequence)
+    } else {
+        None
+    };
+
+    let mut keys = crate::hash(msgs);
+    let mut matched_keys = matched_keys;
+    if let Some(positions) = positions {
+        keys = chain::restore_order(keys, &positions);
+        matched_keys =
+            matched_keys.map(|matched_keys| chain::restore_order(matched_keys, &positions));
+    }
+    let detailed =
+        options.checkpoint || forks.is_some() || warnings.is_some() || matched_keys.is_some();
+    let report = BatchReport {
+        keys,
+        anchored_at,
+        matched_keys,
+        forks,
+        warnings,
+        ..BatchReport::default()
+    };
+    Ok((report, detailed))
+}
+
+/// Check each message value as by `ssb_validate` with some of the rules disabled (see
+/// `rules::check_value`), along with `author` against the first message when the batch is of a
+/// single feed.
+fn check_unlinked_rules(msgs: &[Vec<u8>], rules: &Rules, single_feed: bool) -> Result<(), String> {
+    let mut feed_author = None;
+    for msg in msgs {
+        rules::check_value(msg, rules).map_err(|e| invalid(e, msg))?;
+        if single_feed && rules.author {
+            rules::check_same_author(msg, &mut feed_author).map_err(|e| invalid(e, msg))?;
+        }
+    }
+    Ok(())
+}
+
+/// Verify and validate out-of-order messages of a single feed (see `validateOOOBatch`).
+pub fn validate_out_of_order(
+    hmac_keys: &HmacKeys,
+    elements: Vec<String>,
+    options: &ValidationOptions,
+) -> BatchResult {
+    let batch = into_batch(elements);
+
+    // exact duplicates are only verified and validated once
+    let deduplicated = dedupe::deduplicate(batch);
+    let msgs = &deduplicated.msgs;
+
+    // when a rule other than `signature` is disabled, the rules are checked in place of full
+    // validation
+    let rules = options.rules.filter(|rules| !rules.full_validation());
+    let matched_keys = verify_unless_skipped(msgs, hmac_keys, options)?;
+
+    // forks would otherwise pass validation unnoticed
+    let forks = check_forks(msgs, &[], options)?;
+
+    let warnings = check_timestamps(msgs, options)?;
+
+    if let Some(rules) = &rules {
+        check_unlinked_rules(msgs, rules, true)?;
+    } else if let Err(e) = par_validate_ooo_message_value_hash_chain_of_feed::<_, &[u8]>(msgs, None)
+    {
+        // attempt batch validation and match on error to find invalid message; the `previous`
+        // link of each message is left to `checkLinks` below, since its predecessor may be
+        // missing
+        let invalid_msg = msgs
+            .iter()
+            .find(|msg| validate_ooo_message_value_hash_chain::<_, &[u8]>(msg, None).is_err());
+        return Err(match invalid_msg {
+            Some(msg) => invalid(e, msg),
+            None => format!(
+                "found invalid message: {}: parallel validation failed but no single invalid message was found",
+                e
+            ),
+        });
+    }
+
+    // opportunistically check the links between consecutive messages present in the batch
+    if options.check_links {
+        chain::check_adjacent_links(msgs)?;
+    }
+
+    let feeds = if options.report_gaps {
+        Some(gaps::find_gaps(msgs)?)
+    } else {
+        None
+    };
+
+    let dedupe = options.dedupe;
+    let matched_keys = matched_keys.and_then(|matched_keys| {
+        matched_keys_by_position(matched_keys, &deduplicated, dedupe, hmac_keys)
+    });
+    let detailed = dedupe
+        || feeds.is_some()
+        || forks.is_some()
+        || warnings.is_some()
+        || matched_keys.is_some();
+    let report = BatchReport {
+        feeds,
+        matched_keys,
+        forks,
+        warnings,
+        ..batch_report(deduplicated, dedupe)
+    };
+    Ok((report, detailed))
+}
+
+/// Verify and validate messages of any number of feeds (see `validateMultiAuthorBatch`).
+pub fn validate_multi_author(
+    hmac_keys: &HmacKeys,
+    elements: Vec<String>,
+    options: &ValidationOptions,
+) -> BatchResult {
+    let mut batch = into_batch(elements);
+
+    // messages of other feed formats are dispatched on the format of their author
+    if !format::is_classic_batch(&batch) {
+        return validate_mixed_formats(hmac_keys, batch, options);
+    }
+
+    // drop or reject the messages of disallowed authors before any signature is verified,
+    // remembering the original position of the rest
+    let mut filter_positions = None;
+    if let Some(author_filter) = &options.author_filter {
+        let filtered = author_filter.apply(batch)?;
+        batch = filtered.msgs;
+        if author_filter.skip {
+            filter_positions = Some((filtered.kept, filtered.skipped));
+        }
+    }
+
+    // exact duplicates are only verified and validated once
+    let deduplicated = dedupe::deduplicate(batch);
+    let msgs = &deduplicated.msgs;
+
+    // when a rule other than `signature` is disabled, the rules are checked in place of full
+    // validation
+    let rules = options.rules.filter(|rules| !rules.full_validation());
+    let matched_keys = verify_unless_skipped(msgs, hmac_keys, options)?;
+
+    // forks would otherwise pass validation unnoticed
+    let forks = check_forks(msgs, &[], options)?;
+
+    let warnings = check_timestamps(msgs, options)?;
+
+    if let Some(rules) = &rules {
+        check_unlinked_rules(msgs, rules, false)?;
+    } else if options.chain_per_author {
+        // validate the hash chain of each author, with keys returned in the original input order
+        chain::par_validate_author_chains(msgs)?;
+    } else if let Err(e) = par_validate_message_value(msgs) {
+        // attempt batch validation and match on error to find invalid message
+        let invalid_msg = msgs.iter().find(|msg| validate_message_value(msg).is_err());
+        return Err(match invalid_msg {
+            Some(msg) => invalid(e, msg),
+            None => format!(
+                "found invalid message: {}: parallel validation failed but no single invalid message was found",
+                e
+            ),
+        });
+    }
+
+    let dedupe = options.dedupe;
+    let matched_keys = matched_keys.and_then(|matched_keys| {
+        matched_keys_by_position(matched_keys, &deduplicated, dedupe, hmac_keys)
+    });
+    let detailed = dedupe
+        || forks.is_some()
+        || warnings.is_some()
+        || filter_positions.is_some()
+        || matched_keys.is_some();
+    let mut report = BatchReport {
+        forks,
+        warnings,
+        matched_keys,
+        ..batch_report(deduplicated, dedupe)
+    };
+    // positions of the report refer to the filtered batch; map them back to the input
+    if let Some((kept, skipped)) = filter_positions {
+        report.duplicates = report
+            .duplicates
+            .map(|duplicates| duplicates.iter().map(|idx| kept[*idx]).collect());
+        report.skipped = Some(skipped);
+    }
+    Ok((report, detailed))
+}
+
+/// Verify and validate messages of any number of feeds of any format, each without reference
+/// to the others, dispatching each message on its feed format (see `format`).
+///
+/// Only a single HMAC key, `limits`, `output` and `forks` apply to a batch of mixed formats. An
+/// array or map of HMAC keys, `rules`, `dedupe`, `chainPerAuthor`, `timestampPolicy` and
+/// `authorFilter` are rejected, as is `failFast: false` (whose per-message validation only reads
+/// message values).
+fn validate_mixed_formats(
+    hmac_keys: &HmacKeys,
+    batch: Vec<Vec<u8>>,
+    options: &ValidationOptions,
+) -> BatchResult {
+    let msgs = batch
+        .into_iter()
+        .map(FeedMsg::from_element)
+        .collect::<Result<Vec<FeedMsg>, String>>()?;
+    let formats = msgs
+        .iter()
+        .map(FeedMsg::format)
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let unsupported = [
+        ("rules", options.rules.is_some()),
+        ("chainPerAuthor", options.chain_per_author),
+        ("dedupe", options.dedupe),
+        ("timestampPolicy", options.timestamp_policy.is_some()),
+        ("authorFilter", options.author_filter.is_some()),
+    ];
+    if let Some((name, _)) = unsupported.iter().find(|(_, given)| *given) {
+        return Err(format!(
+            "options invalid: {} does not apply to a batch of mixed feed formats",
+            name
+        ));
+    }
+    let hmac_key = match hmac_keys {
+        HmacKeys::Single(hmac_key) => hmac_key.as_deref(),
+        _ => {
+            return Err(
+                "hmac key invalid: a batch of mixed feed formats takes a single key".to_string(),
+            )
+        }
+    };
+
+    let validated = msgs
+        .iter()
+        .zip(formats)
+        .map(|(msg, format)| msg.validate_ooo(format, hmac_key))
+        .collect::<Result<Vec<_>, String>>()?;
+
+    // forks would otherwise pass validation unnoticed
+    let forks = match options.forks() {
+        ForkPolicy::Ignore => None,
+        policy => {
+            let positions = validated
+                .iter()
+                .map(|msg| FeedTip {
+                    author: msg.author.clone(),
+                    sequence: msg.sequence,
+                    key: msg.key.clone(),
+                })
+                .collect();
+            apply_fork_policy(fork::find_forks(positions, &[]), policy)?
+        }
+    };
+
+    let detailed = forks.is_some();
+    let report = BatchReport {
+        keys: validated.into_iter().map(|msg| msg.key).collect(),
+        forks,
+        ..BatchReport::default()
+    };
+    Ok((report, detailed))
+}
+
+/// Verify and validate messages of any number of feeds, detecting the strictest validation
+/// which applies to each feed (see `chain::par_validate_auto`). The report always includes the
+/// mode applied to each feed.
+pub fn validate_auto(
+    hmac_keys: &HmacKeys,
+    elements: Vec<String>,
+    options: &ValidationOptions,
+) -> BatchResult {
+    let batch = into_batch(elements);
+
+    // exact duplicates are only verified and validated once
+    let deduplicated = dedupe::deduplicate(batch);
+    let msgs = &deduplicated.msgs;
+
+    let matched_keys = verify_all(msgs, hmac_keys)?;
+
+    // forks would otherwise be mistaken for a sparse feed
+    let forks = check_forks(msgs, &[], options)?;
+
+    let warnings = check_timestamps(msgs, options)?;
+
+    let groups = chain::par_validate_auto(msgs)?;
+
+    let dedupe = options.dedupe;
+    let matched_keys = matched_keys_by_position(matched_keys, &deduplicated, dedupe, hmac_keys);
+    let report = BatchReport {
+        groups: Some(groups),
+        matched_keys,
+        forks,
+        warnings,
+        ..batch_report(deduplicated, dedupe)
+    };
+    Ok((report, true))
+}
+
+/// Verify and validate every message of the batch, reporting each invalid message rather than
+/// stopping at the first (`failFast: false`).
+///
+/// Only the checks made one message at a time are applied: the signature, the message value
+/// and, for an ordered batch, the link to the preceding valid message. A key is reported for
+/// every position of the batch, including the invalid ones.
+pub fn validate_collect_all(
+    hmac_keys: &HmacKeys,
+    elements: Vec<String>,
+    options: &ValidationOptions,
+) -> BatchResult {
+    let mut msgs = into_batch(elements);
+    if !format::is_classic_batch(&msgs) {
+        return Err(
+            "options invalid: `failFast: false` does not apply to a batch of mixed feed formats"
+                .to_string(),
+        );
+    }
+
+    let mut positions = None;
+    if options.auto_sort {
+        let (sorted, original_positions) = chain::sort_by_sequence(msgs)?;
+        msgs = sorted;
+        positions = Some(original_positions);
+    }
+
+    // the tip of an ordered feed only advances past valid messages
+    let mut tip = match &options.previous {
+        Some(previous) => Some(Previous::parse(previous.clone().into_bytes()).tip()?),
+        None => None,
+    };
+
+    let mut invalid_msgs = Vec::new();
+    for (idx, msg) in msgs.iter().enumerate() {
+        let result = hmac_keys.verify(msg).and_then(|_| {
+            match options.ordering() {
+                Ordering::Ordered if idx == 0 && options.checkpoint => validate_checkpoint(msg)
+                    .and_then(|_| FeedTip::from_message(msg))
+                    .map(|new_tip| tip = Some(new_tip)),
+                Ordering::Ordered => match &tip {
+                    Some(previous) => state::validate_against_tip(msg, previous),
+                    None => validate_message_value_hash_chain::<_, &[u8]>(msg, None)
+                        .map_err(|e| e.to_string())
+                        .and_then(|_| FeedTip::from_message(msg)),
+                }
+                .map(|new_tip| tip = Some(new_tip)),
+                // (ordering auto is rejected along with `failFast: false`)
+                Ordering::OutOfOrder | Ordering::Auto => {
+                    validate_ooo_message_value_hash_chain::<_, &[u8]>(msg, None)
+                        .map_err(|e| e.to_string())
+                }
+                Ordering::MultiAuthor => validate_message_value(msg).map_err(|e| e.to_string()),
+            }
+            .map_err(|e| invalid(e, msg))
+        });
+        if let Err(error) = result {
+            let position = positions.as_ref().map_or(idx, |positions| positions[idx]);
+            invalid_msgs.push(InvalidMessage { position, error });
+        }
+    }
+    invalid_msgs.sort_by_key(|invalid| invalid.position);
+
+    let anchored_at = if options.checkpoint {
+        msgs.first()
+            .and_then(|msg| message::parse_meta(msg).ok())
+            .map(|meta| meta.sequence)
+    } else {
+        None
+    };
+
+    let mut keys = crate::hash(msgs);
+    if let Some(positions) = positions {
+        keys = chain::restore_order(keys, &positions);
+    }
+    let report = BatchReport {
+        keys,
+        anchored_at,
+        invalid: Some(invalid_msgs),
+        ..BatchReport::default()
+    };
+    Ok((report, true))
+}
@@ -0,0 +1,188 @@
+// SPDX-FileCopyrightText: 2021 Andrew 'glyph' Reid
+//
+// SPDX-License-Identifier: LGPL-3.0-only
+
+//! A minimal bencode decoder and encoder, as used by the Bendy Butt feed format.
+
+/// The maximum nesting depth of lists and dictionaries, which bounds the recursion of the
+/// decoder.
+const MAX_DEPTH: usize = 32;
+
+/// A decoded bencode value. Dictionary entries are kept in their encoded order.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Int(i64),
+    Bytes(Vec<u8>),
+    List(Vec<Value>),
+    Dict(Vec<(Vec<u8>, Value)>),
+}
+
+impl Value {
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            Value::Int(int) => Some(*int),
+            _ => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Value::Bytes(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[Value]> {
+        match self {
+            Value::List(list) => Some(list),
+            _ => None,
+        }
+    }
+
+    /// Look up the value of a key of a dictionary.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Dict(entries) => entries
+                .iter()
+                .find(|(entry_key, _)| entry_key == key.as_bytes())
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+}
+
+struct Decoder<'a> {
+    input: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn peek(&self) -> Result<u8, String> {
+        self.input
+            .get(self.pos)
+            .copied()
+            .ok_or_else(|| "bencode invalid: unexpected end of input".to_string())
+    }
+
+    /// Read the digits up to (and consume) the given terminator.
+    fn read_number(&mut self, terminator: u8) -> Result<i64, String> {
+        let start = self.pos;
+        while self.peek()? != terminator {
+            self.pos += 1;
+        }
+        let digits = std::str::from_utf8(&self.input[start..self.pos])
+            .map_err(|_| "bencode invalid: malformed number".to_string())?;
+        self.pos += 1;
+        digits
+            .parse()
+            .map_err(|_| format!("bencode invalid: malformed number ({})", digits))
+    }
+
+    fn read_bytes(&mut self) -> Result<Vec<u8>, String> {
+        let len = self.read_number(b':')?;
+        if len < 0 {
+            return Err("bencode invalid: negative byte string length".to_string());
+        }
+        let end = self
+            .pos
+            .checked_add(len as usize)
+            .filter(|end| *end <= self.input.len())
+            .ok_or_else(|| "bencode invalid: byte string exceeds input".to_string())?;
+        let bytes = self.input[self.pos..end].to_vec();
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    /// Read a value nested within `depth` lists and dictionaries.
+    fn read_value(&mut self, depth: usize) -> Result<Value, String> {
+        let byte = self.peek()?;
+        if (byte == b'l' || byte == b'd') && depth >= MAX_DEPTH {
+            return Err(format!(
+                "bencode invalid: nested more than {} levels deep",
+                MAX_DEPTH
+            ));
+        }
+        match byte {
+            b'i' => {
+                self.pos += 1;
+                Ok(Value::Int(self.read_number(b'e')?))
+            }
+            b'l' => {
+                self.pos += 1;
+                let mut list = Vec::new();
+                while self.peek()? != b'e' {
+                    list.push(self.read_value(depth + 1)?);
+                }
+                self.pos += 1;
+                Ok(Value::List(list))
+            }
+            b'd' => {
+                self.pos += 1;
+                let mut entries = Vec::new();
+                while self.peek()? != b'e' {
+                    let key = self.read_bytes()?;
+                    let value = self.read_value(depth + 1)?;
+                    entries.push((key, value));
+                }
+                self.pos += 1;
+                Ok(Value::Dict(entries))
+            }
+            b'0'..=b'9' => Ok(Value::Bytes(self.read_bytes()?)),
+            byte => Err(format!("bencode invalid: unexpected byte ({})", byte)),
+        }
+    }
+}
+
+/// Decode a bencode value, which must make up the whole input and be canonically encoded
+/// (integers without leading zeros, dictionary keys sorted and unique).
+pub fn decode(input: &[u8]) -> Result<Value, String> {
+    let mut decoder = Decoder { input, pos: 0 };
+    let value = decoder.read_value(0)?;
+    if decoder.pos != input.len() {
+        return Err("bencode invalid: trailing bytes after value".to_string());
+    }
+    if encode(&value) != input {
+        return Err("bencode invalid: not canonically encoded".to_string());
+    }
+    Ok(value)
+}
+
+fn encode_into(value: &Value, out: &mut Vec<u8>) {
+    match value {
+        Value::Int(int) => {
+            out.push(b'i');
+            out.extend_from_slice(int.to_string().as_bytes());
+            out.push(b'e');
+        }
+        Value::Bytes(bytes) => {
+            out.extend_from_slice(bytes.len().to_string().as_bytes());
+            out.push(b':');
+            out.extend_from_slice(bytes);
+        }
+        Value::List(list) => {
+            out.push(b'l');
+            for item in list {
+                encode_into(item, out);
+            }
+            out.push(b'e');
+        }
+        Value::Dict(entries) => {
+            let mut sorted: Vec<&(Vec<u8>, Value)> = entries.iter().collect();
+            sorted.sort_by(|a, b| a.0.cmp(&b.0));
+            sorted.dedup_by(|a, b| a.0 == b.0);
+            out.push(b'd');
+            for (key, value) in sorted {
+                encode_into(&Value::Bytes(key.clone()), out);
+                encode_into(value, out);
+            }
+            out.push(b'e');
+        }
+    }
+}
+
+/// Encode a bencode value canonically.
+pub fn encode(value: &Value) -> Vec<u8> {
+    let mut out = Vec::new();
+    encode_into(value, &mut out);
+    out
+}
@@ -0,0 +1,202 @@
+// SPDX-FileCopyrightText: 2021 Andrew 'glyph' Reid
+//
+// SPDX-License-Identifier: LGPL-3.0-only
+
+//! Validation of Bendy Butt messages: the bencoded feed format of metafeeds, with
+//! `.bbfeed-v1` feed IDs and `.bbmsg-v1` message keys.
+//!
+//! A message is the list `[payload, signature]`, where the payload is the list
+//! `[author, sequence, previous, timestamp, contentSection]` and the content section is either
+//! `[content, contentSignature]` or encrypted content. Feed IDs, message keys and signatures
+//! are BFE encoded (see `bfe`).
+
+use crate::{
+    bencode::{self, Value},
+    bfe, crypto,
+    feed::{field, BinaryMsg},
+};
+
+/// The maximum byte length of an encoded message.
+const MAX_MESSAGE_BYTES: usize = 8192;
+
+/// Prepended to the encoded content before the content signature is verified.
+const CONTENT_SIGNATURE_PREFIX: &[u8] = b"bendybutt";
+
+/// The fields of a verified Bendy Butt message.
+pub struct BendyButtMsg {
+    /// Public key of the author.
+    pub author: Vec<u8>,
+    pub sequence: u64,
+    /// Hash of the previous message (`None` for the first message of a feed).
+    pub previous: Option<Vec<u8>>,
+    /// The content dictionary (`None` for encrypted content).
+    pub content: Option<Value>,
+    /// Hash of the message.
+    pub hash: [u8; 32],
+}
+
+impl BinaryMsg for BendyButtMsg {
+    fn verify(msg: &[u8], hmac_key: Option<&[u8]>) -> Result<BendyButtMsg, String> {
+        verify(msg, hmac_key)
+    }
+
+    fn validate_link(&self, previous: Option<&BendyButtMsg>) -> Result<(), String> {
+        validate_link(self, previous)
+    }
+
+    /// The `@...=.bbfeed-v1` ID of the author.
+    fn author_id(&self) -> String {
+        format!("@{}.bbfeed-v1", base64::encode(&self.author))
+    }
+
+    fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    /// The `%...=.bbmsg-v1` key of the message.
+    fn key(&self) -> String {
+        format!("%{}.bbmsg-v1", base64::encode(self.hash))
+    }
+}
+
+/// Decode a message and verify its signature and content signature, without reference to any
+/// other message of the feed.
+pub fn verify(msg: &[u8], hmac_key: Option<&[u8]>) -> Result<BendyButtMsg, String> {
+    if msg.len() > MAX_MESSAGE_BYTES {
+        return Err(format!(
+            "message invalid: byte length ({}) exceeds the maximum of {}",
+            msg.len(),
+            MAX_MESSAGE_BYTES
+        ));
+    }
+
+    let value = bencode::decode(msg)?;
+    let (payload, signature) = match field(value.as_list(), "message")? {
+        [payload, signature] => (payload, signature),
+        _ => return Err("message invalid: must be a list of payload and signature".to_string()),
+    };
+    let (author, sequence, previous, timestamp, content_section) =
+        match field(payload.as_list(), "payload")? {
+            [author, sequence, previous, timestamp, content_section] => {
+                (author, sequence, previous, timestamp, content_section)
+            }
+            _ => return Err("message invalid: payload must be a list of five fields".to_string()),
+        };
+
+    let author = bfe::decode_exact(
+        field(author.as_bytes(), "author")?,
+        bfe::FEED,
+        bfe::FEED_BENDYBUTT,
+        32,
+    )
+    .map_err(|e| format!("author invalid: {}", e))?;
+    let sequence = field(sequence.as_int().filter(|seq| *seq >= 1), "sequence")? as u64;
+    let previous = field(previous.as_bytes(), "previous")?;
+    let previous = if sequence == 1 {
+        if previous != bfe::NIL {
+            return Err("The first message of a feed must have previous of null".to_string());
+        }
+        None
+    } else {
+        let previous = bfe::decode_exact(previous, bfe::MESSAGE, bfe::MESSAGE_BENDYBUTT, 32)
+            .map_err(|e| format!("previous invalid: {}", e))?;
+        Some(previous.to_vec())
+    };
+    field(timestamp.as_int(), "timestamp")?;
+
+    let signature = bfe::decode_exact(
+        field(signature.as_bytes(), "signature")?,
+        bfe::SIGNATURE,
+        bfe::SIGNATURE_ED25519,
+        64,
+    )
+    .map_err(|e| format!("signature invalid: {}", e))?;
+    crypto::verify_detached(author, signature, &bencode::encode(payload), hmac_key)?;
+
+    let content = match content_section {
+        Value::List(section) => match &section[..] {
+            [content @ Value::Dict(_), content_signature] => {
+                verify_content(content, content_signature, hmac_key)?;
+                Some(content.clone())
+            }
+            _ => {
+                return Err(
+                    "content invalid: must be a list of content and content signature".to_string(),
+                )
+            }
+        },
+        Value::Bytes(encrypted) => {
+            bfe::decode(encrypted, bfe::BOX).map_err(|e| format!("content invalid: {}", e))?;
+            None
+        }
+        _ => return Err("content invalid: must be a list or encrypted".to_string()),
+    };
+
+    Ok(BendyButtMsg {
+        author: author.to_vec(),
+        sequence,
+        previous,
+        content,
+        hash: crypto::sha256(msg),
+    })
+}
+
+/// Verify the content signature, made by the subfeed which the content refers to.
+fn verify_content(
+    content: &Value,
+    content_signature: &Value,
+    hmac_key: Option<&[u8]>,
+) -> Result<(), String> {
+    let subfeed = content
+        .get("subfeed")
+        .and_then(Value::as_bytes)
+        .ok_or_else(|| "content invalid: missing subfeed".to_string())?;
+    let (_, subfeed_key) =
+        bfe::decode(subfeed, bfe::FEED).map_err(|e| format!("subfeed invalid: {}", e))?;
+    let content_signature = bfe::decode_exact(
+        field(content_signature.as_bytes(), "content signature")?,
+        bfe::SIGNATURE,
+        bfe::SIGNATURE_ED25519,
+        64,
+    )
+    .map_err(|e| format!("content signature invalid: {}", e))?;
+
+    let mut signed = CONTENT_SIGNATURE_PREFIX.to_vec();
+    signed.extend_from_slice(&bencode::encode(content));
+    crypto::verify_detached(subfeed_key, content_signature, &signed, hmac_key)
+        .map_err(|e| format!("content signature invalid: {}", e))
+}
+
+/// Check that a verified message correctly extends a feed, given the previous message of that
+/// feed (if any).
+pub fn validate_link(msg: &BendyButtMsg, previous: Option<&BendyButtMsg>) -> Result<(), String> {
+    match previous {
+        None => {
+            if msg.sequence != 1 {
+                return Err("The first message of a feed must have seq of 1".to_string());
+            }
+        }
+        Some(previous) => {
+            if msg.author != previous.author {
+                return Err(format!(
+                    "author of message ({}) did not match author of previous message ({})",
+                    msg.author_id(),
+                    previous.author_id()
+                ));
+            }
+            if previous.sequence.checked_add(1) != Some(msg.sequence) {
+                return Err(format!(
+                    "sequence of message ({}) is not one greater than that of previous message ({})",
+                    msg.sequence, previous.sequence
+                ));
+            }
+            if msg.previous.as_deref() != Some(&previous.hash[..]) {
+                return Err(format!(
+                    "previous of message did not match key of previous message ({})",
+                    previous.key()
+                ));
+            }
+        }
+    }
+    Ok(())
+}
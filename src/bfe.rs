@@ -0,0 +1,87 @@
+// SPDX-FileCopyrightText: 2021 Andrew 'glyph' Reid
+//
+// SPDX-License-Identifier: LGPL-3.0-only
+
+//! Binary Field Encodings (BFE): the type and format prefixed byte encodings of feed IDs,
+//! message keys, signatures and other values used by the binary feed formats.
+
+/// BFE type of feed IDs.
+pub const FEED: u8 = 0x00;
+/// BFE type of message keys.
+pub const MESSAGE: u8 = 0x01;
+/// BFE type of signatures.
+pub const SIGNATURE: u8 = 0x04;
+/// BFE type of encrypted content.
+pub const BOX: u8 = 0x05;
+/// BFE type of generic values.
+pub const VALUE: u8 = 0x06;
+
+/// BFE format of classic feed IDs.
+pub const FEED_CLASSIC: u8 = 0x00;
+/// BFE format of Gabby Grove feed IDs.
+pub const FEED_GABBYGROVE: u8 = 0x01;
+/// BFE format of Bendy Butt feed IDs and message keys.
+pub const FEED_BENDYBUTT: u8 = 0x03;
+pub const MESSAGE_BENDYBUTT: u8 = 0x04;
+/// BFE format of buttwoo feed IDs and message keys.
+pub const FEED_BUTTWOO: u8 = 0x04;
+pub const MESSAGE_BUTTWOO: u8 = 0x05;
+/// BFE format of ed25519 message signatures.
+pub const SIGNATURE_ED25519: u8 = 0x00;
+/// BFE format of the nil value.
+pub const VALUE_NIL: u8 = 0x02;
+
+/// The encoded nil value.
+pub const NIL: [u8; 2] = [VALUE, VALUE_NIL];
+
+/// Split a BFE value of the given type into its format and data.
+pub fn decode(bytes: &[u8], bfe_type: u8) -> Result<(u8, &[u8]), String> {
+    match bytes {
+        [found_type, format, data @ ..] if *found_type == bfe_type => Ok((*format, data)),
+        [found_type, ..] => Err(format!(
+            "expected bfe type {} but found type {}",
+            bfe_type, found_type
+        )),
+        _ => Err("bfe value invalid: too short".to_string()),
+    }
+}
+
+/// Decode a BFE value of the given type and format holding exactly `len` bytes of data.
+pub fn decode_exact(bytes: &[u8], bfe_type: u8, format: u8, len: usize) -> Result<&[u8], String> {
+    let (found_format, data) = decode(bytes, bfe_type)?;
+    if found_format != format {
+        return Err(format!(
+            "expected bfe format {} of type {} but found format {}",
+            format, bfe_type, found_format
+        ));
+    }
+    if data.len() != len {
+        return Err(format!(
+            "bfe value invalid: expected {} bytes of data but found {}",
+            len,
+            data.len()
+        ));
+    }
+    Ok(data)
+}
+
+/// Decode a BFE feed ID into its string form (`@...=.ed25519`, `ssb:feed/buttwoo-v1/...` etc.).
+pub fn feed_id(bytes: &[u8]) -> Result<String, String> {
+    let (format, key) = decode(bytes, FEED)?;
+    if key.len() != 32 {
+        return Err(format!(
+            "bfe value invalid: expected 32 bytes of data but found {}",
+            key.len()
+        ));
+    }
+    match format {
+        FEED_CLASSIC => Ok(format!("@{}.ed25519", base64::encode(key))),
+        FEED_GABBYGROVE => Ok(format!("@{}.ggfeed-v1", base64::encode(key))),
+        FEED_BENDYBUTT => Ok(format!("@{}.bbfeed-v1", base64::encode(key))),
+        FEED_BUTTWOO => Ok(format!(
+            "ssb:feed/buttwoo-v1/{}",
+            base64::encode_config(key, base64::URL_SAFE_NO_PAD)
+        )),
+        _ => Err(format!("unsupported feed format: bfe format {}", format)),
+    }
+}
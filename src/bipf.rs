@@ -0,0 +1,149 @@
+// SPDX-FileCopyrightText: 2021 Andrew 'glyph' Reid
+//
+// SPDX-License-Identifier: LGPL-3.0-only
+
+//! A minimal BIPF (Binary In-Place Format) decoder, as used by the buttwoo feed format.
+//!
+//! Each value is a varint of `length << 3 | type` followed by `length` bytes of data.
+
+const STRING: u64 = 0;
+const BUFFER: u64 = 1;
+const INT: u64 = 2;
+const DOUBLE: u64 = 3;
+const ARRAY: u64 = 4;
+const OBJECT: u64 = 5;
+const BOOLNULL: u64 = 6;
+
+/// The maximum nesting depth of arrays and objects, which bounds the recursion of the decoder.
+const MAX_DEPTH: usize = 32;
+
+/// A decoded BIPF value. Object entries are kept in their encoded order.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    String(String),
+    Buffer(Vec<u8>),
+    Int(i32),
+    Double(f64),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+    Bool(bool),
+    Null,
+}
+
+impl Value {
+    /// The value of an integer, or of a double holding a whole number.
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            Value::Int(int) => Some(i64::from(*int)),
+            Value::Double(double) if double.fract() == 0.0 => Some(*double as i64),
+            _ => None,
+        }
+    }
+
+    pub fn as_buffer(&self) -> Option<&[u8]> {
+        match self {
+            Value::Buffer(buffer) => Some(buffer),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Value::Array(array) => Some(array),
+            _ => None,
+        }
+    }
+}
+
+/// Read an unsigned LEB128 varint, returning it along with the number of bytes read.
+fn read_varint(input: &[u8]) -> Result<(u64, usize), String> {
+    let mut value: u64 = 0;
+    for (idx, byte) in input.iter().enumerate().take(10) {
+        value |= u64::from(byte & 0x7f) << (7 * idx);
+        if byte & 0x80 == 0 {
+            return Ok((value, idx + 1));
+        }
+    }
+    Err("bipf invalid: malformed varint".to_string())
+}
+
+/// Decode the value at the start of the input, nested within `depth` arrays and objects,
+/// returning it along with its encoded length.
+fn read_value(input: &[u8], depth: usize) -> Result<(Value, usize), String> {
+    let (tag, header_len) = read_varint(input)?;
+    if (tag & 7 == ARRAY || tag & 7 == OBJECT) && depth >= MAX_DEPTH {
+        return Err(format!(
+            "bipf invalid: nested more than {} levels deep",
+            MAX_DEPTH
+        ));
+    }
+    let len = (tag >> 3) as usize;
+    let end = header_len
+        .checked_add(len)
+        .filter(|end| *end <= input.len())
+        .ok_or_else(|| "bipf invalid: value exceeds input".to_string())?;
+    let data = &input[header_len..end];
+
+    let value = match tag & 7 {
+        STRING => Value::String(
+            String::from_utf8(data.to_vec())
+                .map_err(|_| "bipf invalid: string is not valid utf8".to_string())?,
+        ),
+        BUFFER => Value::Buffer(data.to_vec()),
+        INT => match data {
+            [a, b, c, d] => Value::Int(i32::from_le_bytes([*a, *b, *c, *d])),
+            _ => return Err("bipf invalid: int must be 4 bytes".to_string()),
+        },
+        DOUBLE => {
+            let mut bytes = [0; 8];
+            if data.len() != 8 {
+                return Err("bipf invalid: double must be 8 bytes".to_string());
+            }
+            bytes.copy_from_slice(data);
+            Value::Double(f64::from_le_bytes(bytes))
+        }
+        ARRAY => {
+            let mut array = Vec::new();
+            let mut pos = 0;
+            while pos < data.len() {
+                let (item, item_len) = read_value(&data[pos..], depth + 1)?;
+                array.push(item);
+                pos += item_len;
+            }
+            Value::Array(array)
+        }
+        OBJECT => {
+            let mut entries = Vec::new();
+            let mut pos = 0;
+            while pos < data.len() {
+                let (key, key_len) = read_value(&data[pos..], depth + 1)?;
+                pos += key_len;
+                let key = match key {
+                    Value::String(key) => key,
+                    _ => return Err("bipf invalid: object key must be a string".to_string()),
+                };
+                let (value, value_len) = read_value(&data[pos..], depth + 1)?;
+                pos += value_len;
+                entries.push((key, value));
+            }
+            Value::Object(entries)
+        }
+        BOOLNULL => match data {
+            [] => Value::Null,
+            [0] => Value::Bool(false),
+            [1] => Value::Bool(true),
+            _ => return Err("bipf invalid: malformed boolean".to_string()),
+        },
+        _ => return Err("bipf invalid: reserved type".to_string()),
+    };
+    Ok((value, end))
+}
+
+/// Decode a BIPF value, which must make up the whole input.
+pub fn decode(input: &[u8]) -> Result<Value, String> {
+    let (value, len) = read_value(input, 0)?;
+    if len != input.len() {
+        return Err("bipf invalid: trailing bytes after value".to_string());
+    }
+    Ok(value)
+}
@@ -0,0 +1,228 @@
+// SPDX-FileCopyrightText: 2021 Andrew 'glyph' Reid
+//
+// SPDX-License-Identifier: LGPL-3.0-only
+
+//! Validation of buttwoo messages: a BIPF encoded feed format with BLAKE3 message keys,
+//! identified by `ssb:feed/buttwoo-v1/...` and `ssb:message/buttwoo-v1/...` URIs.
+//!
+//! A message is the BIPF array `[value, signature, content]`, where `value` is itself the BIPF
+//! array `[author, parent, sequence, timestamp, previous, tag, contentLength, contentHash]`.
+//! The signature is made over the encoded value and the key of the message is the BLAKE3 hash
+//! of the encoded value followed by the signature.
+
+use crate::{
+    bfe,
+    bipf::{self, Value},
+    crypto,
+    feed::{field, BinaryMsg},
+};
+
+/// The maximum byte length of the content of a message.
+const MAX_CONTENT_BYTES: usize = 16384;
+/// The maximum byte length of a message: the largest content, with room for the value and
+/// signature.
+const MAX_MESSAGE_BYTES: usize = MAX_CONTENT_BYTES + 1024;
+
+/// Tag of a regular message.
+const TAG_STANDARD: u8 = 0x00;
+/// Tag of the last message of a feed.
+const TAG_END_OF_FEED: u8 = 0x01;
+
+/// Prefix of a content hash made with BLAKE3.
+const CONTENT_HASH_BLAKE3: u8 = 0x00;
+
+/// The fields of a verified buttwoo message.
+pub struct ButtwooMsg {
+    /// Public key of the author.
+    pub author: Vec<u8>,
+    /// BFE encoded key of the parent message (nil for a feed without a parent).
+    pub parent: Vec<u8>,
+    pub sequence: u64,
+    /// Hash of the previous message (`None` for the first message of a feed).
+    pub previous: Option<Vec<u8>>,
+    pub tag: u8,
+    /// Hash of the message.
+    pub hash: [u8; 32],
+}
+
+impl BinaryMsg for ButtwooMsg {
+    fn verify(msg: &[u8], hmac_key: Option<&[u8]>) -> Result<ButtwooMsg, String> {
+        verify(msg, hmac_key)
+    }
+
+    fn validate_link(&self, previous: Option<&ButtwooMsg>) -> Result<(), String> {
+        validate_link(self, previous)
+    }
+
+    /// The `ssb:feed/buttwoo-v1/...` ID of the author.
+    fn author_id(&self) -> String {
+        format!(
+            "ssb:feed/buttwoo-v1/{}",
+            base64::encode_config(&self.author, base64::URL_SAFE_NO_PAD)
+        )
+    }
+
+    fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    /// The `ssb:message/buttwoo-v1/...` key of the message.
+    fn key(&self) -> String {
+        format!(
+            "ssb:message/buttwoo-v1/{}",
+            base64::encode_config(self.hash, base64::URL_SAFE_NO_PAD)
+        )
+    }
+}
+
+/// Decode a message and verify its signature and content hash, without reference to any other
+/// message of the feed.
+pub fn verify(msg: &[u8], hmac_key: Option<&[u8]>) -> Result<ButtwooMsg, String> {
+    if msg.len() > MAX_MESSAGE_BYTES {
+        return Err(format!(
+            "message invalid: byte length ({}) exceeds the maximum of {}",
+            msg.len(),
+            MAX_MESSAGE_BYTES
+        ));
+    }
+
+    let (encoded_value, signature, content) = match &bipf::decode(msg)? {
+        Value::Array(parts) => match &parts[..] {
+            [Value::Buffer(value), Value::Buffer(signature), content] => {
+                (value.clone(), signature.clone(), content.clone())
+            }
+            _ => {
+                return Err(
+                    "message invalid: must be an array of value, signature and content".to_string(),
+                )
+            }
+        },
+        _ => return Err("message invalid: must be an array".to_string()),
+    };
+
+    let value = bipf::decode(&encoded_value)?;
+    let (author, parent, sequence, timestamp, previous, tag, content_length, content_hash) =
+        match field(value.as_array(), "value")? {
+            [author, parent, sequence, timestamp, previous, tag, content_length, content_hash] => (
+                author,
+                parent,
+                sequence,
+                timestamp,
+                previous,
+                tag,
+                content_length,
+                content_hash,
+            ),
+            _ => return Err("message invalid: value must be an array of eight fields".to_string()),
+        };
+
+    let author = bfe::decode_exact(
+        field(author.as_buffer(), "author")?,
+        bfe::FEED,
+        bfe::FEED_BUTTWOO,
+        32,
+    )
+    .map_err(|e| format!("author invalid: {}", e))?;
+    let parent = field(parent.as_buffer(), "parent")?;
+    if parent != bfe::NIL {
+        bfe::decode(parent, bfe::MESSAGE).map_err(|e| format!("parent invalid: {}", e))?;
+    }
+    let sequence = field(sequence.as_int().filter(|seq| *seq >= 1), "sequence")? as u64;
+    field(timestamp.as_int(), "timestamp")?;
+    let previous = field(previous.as_buffer(), "previous")?;
+    let previous = if sequence == 1 {
+        if previous != bfe::NIL {
+            return Err("The first message of a feed must have previous of null".to_string());
+        }
+        None
+    } else {
+        let previous = bfe::decode_exact(previous, bfe::MESSAGE, bfe::MESSAGE_BUTTWOO, 32)
+            .map_err(|e| format!("previous invalid: {}", e))?;
+        Some(previous.to_vec())
+    };
+    let tag = match field(tag.as_buffer(), "tag")? {
+        [tag] if *tag == TAG_STANDARD || *tag == TAG_END_OF_FEED => *tag,
+        _ => return Err("message invalid: unknown tag".to_string()),
+    };
+    let content_length = field(content_length.as_int(), "content length")? as usize;
+    if content_length > MAX_CONTENT_BYTES {
+        return Err(format!(
+            "content invalid: byte length ({}) exceeds the maximum of {}",
+            content_length, MAX_CONTENT_BYTES
+        ));
+    }
+    let content_hash = field(content_hash.as_buffer(), "content hash")?;
+
+    if signature.len() != 64 {
+        return Err("signature invalid: must be 64 bytes".to_string());
+    }
+    crypto::verify_detached(author, &signature, &encoded_value, hmac_key)?;
+
+    // the content may have been dropped, leaving only its hash
+    match &content {
+        Value::Null => (),
+        Value::Buffer(content) => {
+            if content.len() != content_length {
+                return Err(format!(
+                    "content invalid: byte length ({}) does not match content length ({})",
+                    content.len(),
+                    content_length
+                ));
+            }
+            let mut expected_hash = vec![CONTENT_HASH_BLAKE3];
+            expected_hash.extend_from_slice(&crypto::blake3(content));
+            if content_hash != &expected_hash[..] {
+                return Err("content invalid: does not match content hash".to_string());
+            }
+        }
+        _ => return Err("content invalid: must be a buffer or null".to_string()),
+    }
+
+    let mut signed = encoded_value.clone();
+    signed.extend_from_slice(&signature);
+    Ok(ButtwooMsg {
+        author: author.to_vec(),
+        parent: parent.to_vec(),
+        sequence,
+        previous,
+        tag,
+        hash: crypto::blake3(&signed),
+    })
+}
+
+/// Check that a verified message correctly extends a feed, given the previous message of that
+/// feed (if any).
+pub fn validate_link(msg: &ButtwooMsg, previous: Option<&ButtwooMsg>) -> Result<(), String> {
+    match previous {
+        None => {
+            if msg.sequence != 1 {
+                return Err("The first message of a feed must have seq of 1".to_string());
+            }
+        }
+        Some(previous) => {
+            if msg.author != previous.author || msg.parent != previous.parent {
+                return Err(format!(
+                    "author of message ({}) did not match author of previous message ({})",
+                    msg.author_id(),
+                    previous.author_id()
+                ));
+            }
+            if previous.tag == TAG_END_OF_FEED {
+                return Err("previous message ended the feed".to_string());
+            }
+            if previous.sequence.checked_add(1) != Some(msg.sequence) {
+                return Err(format!(
+                    "sequence of message ({}) is not one greater than that of previous message ({})",
+                    msg.sequence, previous.sequence
+                ));
+            }
+            if msg.previous.as_deref() != Some(&previous.hash[..]) {
+                return Err(format!(
+                    "previous of message did not match key of previous message ({})",
+                    previous.key()
+                ));
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,175 @@
+// SPDX-FileCopyrightText: 2021 Andrew 'glyph' Reid
+//
+// SPDX-License-Identifier: LGPL-3.0-only
+
+//! A minimal CBOR decoder, as used by the Gabby Grove feed format.
+//!
+//! Only definite-length items are supported; floating point values are rejected since no field
+//! of a Gabby Grove event holds one.
+
+const UNSIGNED: u8 = 0;
+const NEGATIVE: u8 = 1;
+const BYTES: u8 = 2;
+const TEXT: u8 = 3;
+const ARRAY: u8 = 4;
+const MAP: u8 = 5;
+const TAG: u8 = 6;
+const SIMPLE: u8 = 7;
+
+/// A decoded CBOR value. Map entries are kept in their encoded order.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Int(i128),
+    Bytes(Vec<u8>),
+    Text(String),
+    Array(Vec<Value>),
+    Map(Vec<(Value, Value)>),
+    Tag(u64, Box<Value>),
+    Bool(bool),
+    Null,
+}
+
+impl Value {
+    pub fn as_int(&self) -> Option<i128> {
+        match self {
+            Value::Int(int) => Some(*int),
+            _ => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Value::Bytes(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Value::Array(array) => Some(array),
+            _ => None,
+        }
+    }
+
+    /// The content of a value with the given tag.
+    pub fn as_tagged(&self, tag: u64) -> Option<&Value> {
+        match self {
+            Value::Tag(value_tag, value) if *value_tag == tag => Some(value),
+            _ => None,
+        }
+    }
+}
+
+/// The maximum nesting depth of arrays, maps and tags, which bounds the recursion of the
+/// decoder.
+const MAX_DEPTH: usize = 32;
+
+struct Decoder<'a> {
+    input: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|end| *end <= self.input.len())
+            .ok_or_else(|| "cbor invalid: unexpected end of input".to_string())?;
+        let bytes = &self.input[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    /// Read the argument of an item with the given additional information.
+    fn read_argument(&mut self, info: u8) -> Result<u64, String> {
+        let len = match info {
+            0..=23 => return Ok(u64::from(info)),
+            24 => 1,
+            25 => 2,
+            26 => 4,
+            27 => 8,
+            _ => return Err("cbor invalid: indefinite lengths are not supported".to_string()),
+        };
+        Ok(self
+            .take(len)?
+            .iter()
+            .fold(0, |value, byte| value << 8 | u64::from(*byte)))
+    }
+
+    fn read_len(&mut self, info: u8) -> Result<usize, String> {
+        let len = self.read_argument(info)? as usize;
+        // every item takes at least one byte, so a longer length cannot be satisfied
+        if len > self.input.len() - self.pos {
+            return Err("cbor invalid: length exceeds input".to_string());
+        }
+        Ok(len)
+    }
+
+    /// Read a value nested within `depth` arrays, maps and tags.
+    fn read_value(&mut self, depth: usize) -> Result<Value, String> {
+        let initial = self.take(1)?[0];
+        let (major, info) = (initial >> 5, initial & 0x1f);
+        if (major == ARRAY || major == MAP || major == TAG) && depth >= MAX_DEPTH {
+            return Err(format!(
+                "cbor invalid: nested more than {} levels deep",
+                MAX_DEPTH
+            ));
+        }
+        let value = match major {
+            UNSIGNED => Value::Int(i128::from(self.read_argument(info)?)),
+            NEGATIVE => Value::Int(-1 - i128::from(self.read_argument(info)?)),
+            BYTES => {
+                let len = self.read_len(info)?;
+                Value::Bytes(self.take(len)?.to_vec())
+            }
+            TEXT => {
+                let len = self.read_len(info)?;
+                Value::Text(
+                    String::from_utf8(self.take(len)?.to_vec())
+                        .map_err(|_| "cbor invalid: text is not valid utf8".to_string())?,
+                )
+            }
+            ARRAY => {
+                let len = self.read_len(info)?;
+                let mut array = Vec::with_capacity(len);
+                for _ in 0..len {
+                    array.push(self.read_value(depth + 1)?);
+                }
+                Value::Array(array)
+            }
+            MAP => {
+                let len = self.read_len(info)?;
+                let mut entries = Vec::with_capacity(len);
+                for _ in 0..len {
+                    let key = self.read_value(depth + 1)?;
+                    let value = self.read_value(depth + 1)?;
+                    entries.push((key, value));
+                }
+                Value::Map(entries)
+            }
+            TAG => {
+                let tag = self.read_argument(info)?;
+                Value::Tag(tag, Box::new(self.read_value(depth + 1)?))
+            }
+            SIMPLE => match info {
+                20 => Value::Bool(false),
+                21 => Value::Bool(true),
+                22 => Value::Null,
+                _ => return Err("cbor invalid: unsupported simple or float value".to_string()),
+            },
+            _ => unreachable!("major type is three bits"),
+        };
+        Ok(value)
+    }
+}
+
+/// Decode a CBOR value, which must make up the whole input.
+pub fn decode(input: &[u8]) -> Result<Value, String> {
+    let mut decoder = Decoder { input, pos: 0 };
+    let value = decoder.read_value(0)?;
+    if decoder.pos != input.len() {
+        return Err("cbor invalid: trailing bytes after value".to_string());
+    }
+    Ok(value)
+}
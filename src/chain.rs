@@ -0,0 +1,226 @@
+// SPDX-FileCopyrightText: 2021 Andrew 'glyph' Reid
+//
+// SPDX-License-Identifier: LGPL-3.0-only
+
+use std::collections::{BTreeMap, HashMap};
+
+use rayon::prelude::*;
+use serde::Serialize;
+use ssb_validate::message_value::{
+    validate_message_value_hash_chain, validate_ooo_message_value_hash_chain,
+};
+
+use crate::{
+    message,
+    state::{validate_link, FeedTip},
+};
+
+/// Check the hash-chain link between every pair of messages in a batch with consecutive
+/// sequence numbers (by the same author), regardless of the order of the batch.
+///
+/// Messages whose predecessor is not in the batch are not checked. If a link is broken, the
+/// cause of the error is returned along with the offending message.
+pub fn check_adjacent_links(msgs: &[Vec<u8>]) -> Result<(), String> {
+    let mut tips: HashMap<(String, u64), FeedTip> = HashMap::new();
+    let mut metas = Vec::with_capacity(msgs.len());
+    for msg in msgs {
+        let meta = message::parse_meta(msg)?;
+        tips.entry((meta.author.clone(), meta.sequence))
+            .or_insert_with(|| FeedTip {
+                author: meta.author.clone(),
+                sequence: meta.sequence,
+                key: message::key(msg),
+            });
+        metas.push(meta);
+    }
+
+    for (msg, meta) in msgs.iter().zip(metas) {
+        if meta.sequence < 2 {
+            continue;
+        }
+        if let Some(tip) = tips.get(&(meta.author, meta.sequence - 1)) {
+            validate_link(msg, Some(tip))
+                .map_err(|e| format!("found invalid message: {}: {}", e, message::as_str(msg)))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Validate the hash chain of every author in a multi-author batch.
+///
+/// Messages are grouped by author and each group is sorted by sequence number, then the groups
+/// are validated in parallel. The first message of each group is validated without a
+/// predecessor (unless it is the first message of the feed) and every following message is
+/// validated against the one before it, so each group must be a contiguous run of its feed.
+/// If validation fails, the cause of the error is returned along with the offending message;
+/// when several groups fail, the error for the message earliest in the input is returned.
+pub fn par_validate_author_chains(msgs: &[Vec<u8>]) -> Result<(), String> {
+    let mut groups: HashMap<String, Vec<(u64, usize)>> = HashMap::new();
+    for (idx, msg) in msgs.iter().enumerate() {
+        let meta = message::parse_meta(msg)
+            .map_err(|e| format!("found invalid message: {}: {}", e, message::as_str(msg)))?;
+        groups
+            .entry(meta.author)
+            .or_default()
+            .push((meta.sequence, idx));
+    }
+
+    let first_error = groups
+        .par_iter_mut()
+        .filter_map(|(_, group)| {
+            group.sort_unstable();
+            validate_run(msgs, group).err()
+        })
+        .min_by_key(|(idx, _)| *idx);
+
+    match first_error {
+        Some((idx, e)) => Err(format!(
+            "found invalid message: {}: {}",
+            e,
+            message::as_str(&msgs[idx])
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Validate a run of one feed, given as `(sequence, index)` pairs sorted by sequence number: the
+/// first message without a predecessor (unless it is the first message of the feed) and every
+/// following message against the one before it. Returns the index and cause of the first error.
+fn validate_run(msgs: &[Vec<u8>], run: &[(u64, usize)]) -> Result<(), (usize, String)> {
+    for (n, (sequence, idx)) in run.iter().enumerate() {
+        let msg = &msgs[*idx];
+        let result = if n > 0 {
+            let previous = &msgs[run[n - 1].1];
+            validate_message_value_hash_chain(msg, Some(previous))
+        } else if *sequence == 1 {
+            validate_message_value_hash_chain::<_, &[u8]>(msg, None)
+        } else {
+            validate_ooo_message_value_hash_chain::<_, &[u8]>(msg, None)
+        };
+        result.map_err(|e| (*idx, e.to_string()))?;
+    }
+    Ok(())
+}
+
+/// How the messages of one feed were validated when the mode is detected automatically.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ChainMode {
+    /// The messages form a contiguous run, validated as a hash chain.
+    FullChain,
+    /// The messages are sparse: each is validated on its own and the links between those with
+    /// consecutive sequence numbers are checked.
+    OutOfOrder,
+}
+
+/// The validation mode applied to one feed of a batch.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChainGroup {
+    pub author: String,
+    pub mode: ChainMode,
+    pub min_sequence: u64,
+    pub max_sequence: u64,
+    pub count: usize,
+}
+
+/// Validate a batch of any number of feeds, in any order, with the strictest validation which
+/// applies to each feed.
+///
+/// Messages are grouped by author and each group is sorted by sequence number. A group forming
+/// a contiguous run is validated as a hash chain (see `par_validate_author_chains`); any other
+/// group is validated out-of-order, with the links between messages with consecutive sequence
+/// numbers checked (see `check_adjacent_links`). Exact duplicates must be removed beforehand.
+/// Returns the mode applied to each feed, sorted by author; if validation fails, the error for
+/// the message earliest in the input is returned along with the offending message.
+pub fn par_validate_auto(msgs: &[Vec<u8>]) -> Result<Vec<ChainGroup>, String> {
+    let mut groups: BTreeMap<String, Vec<(u64, usize)>> = BTreeMap::new();
+    for (idx, msg) in msgs.iter().enumerate() {
+        let meta = message::parse_meta(msg)
+            .map_err(|e| format!("found invalid message: {}: {}", e, message::as_str(msg)))?;
+        groups
+            .entry(meta.author)
+            .or_default()
+            .push((meta.sequence, idx));
+    }
+
+    let results: Vec<Result<ChainGroup, (usize, String)>> = groups
+        .into_par_iter()
+        .map(|(author, mut group)| {
+            group.sort_unstable();
+            let contiguous = group.windows(2).all(|pair| pair[1].0 == pair[0].0 + 1);
+            let mode = if contiguous {
+                validate_run(msgs, &group)?;
+                ChainMode::FullChain
+            } else {
+                for (_, idx) in &group {
+                    validate_ooo_message_value_hash_chain::<_, &[u8]>(&msgs[*idx], None)
+                        .map_err(|e| (*idx, e.to_string()))?;
+                }
+                for pair in group.windows(2) {
+                    let ((prev_sequence, prev_idx), (sequence, idx)) = (pair[0], pair[1]);
+                    if sequence == prev_sequence + 1 {
+                        let tip = FeedTip::from_message(&msgs[prev_idx]).map_err(|e| (idx, e))?;
+                        validate_link(&msgs[idx], Some(&tip)).map_err(|e| (idx, e))?;
+                    }
+                }
+                ChainMode::OutOfOrder
+            };
+            Ok(ChainGroup {
+                author,
+                mode,
+                min_sequence: group.first().map_or(0, |(sequence, _)| *sequence),
+                max_sequence: group.last().map_or(0, |(sequence, _)| *sequence),
+                count: group.len(),
+            })
+        })
+        .collect();
+
+    let mut chain_groups = Vec::with_capacity(results.len());
+    let mut errors = Vec::new();
+    for result in results {
+        match result {
+            Ok(group) => chain_groups.push(group),
+            Err(error) => errors.push(error),
+        }
+    }
+    let first_error = errors.into_iter().min_by_key(|(idx, _)| *idx);
+
+    match first_error {
+        Some((idx, e)) => Err(format!(
+            "found invalid message: {}: {}",
+            e,
+            message::as_str(&msgs[idx])
+        )),
+        None => Ok(chain_groups),
+    }
+}
+
+/// Sort the messages of a single-author batch by sequence number.
+///
+/// Returns the sorted messages along with the original position of each, so that results can
+/// be mapped back to the order of the input with `restore_order`.
+pub fn sort_by_sequence(msgs: Vec<Vec<u8>>) -> Result<(Vec<Vec<u8>>, Vec<usize>), String> {
+    let mut indexed = Vec::with_capacity(msgs.len());
+    for (position, msg) in msgs.into_iter().enumerate() {
+        let meta = message::parse_meta(&msg)
+            .map_err(|e| format!("found invalid message: {}: {}", e, message::as_str(&msg)))?;
+        indexed.push((meta.sequence, position, msg));
+    }
+    indexed.sort_by_key(|(sequence, position, _)| (*sequence, *position));
+
+    let positions = indexed.iter().map(|(_, position, _)| *position).collect();
+    let sorted = indexed.into_iter().map(|(_, _, msg)| msg).collect();
+    Ok((sorted, positions))
+}
+
+/// Put results computed for sorted messages back into the order of the original input, given
+/// the original position of each sorted message.
+pub fn restore_order<T: Default + Clone>(sorted: Vec<T>, positions: &[usize]) -> Vec<T> {
+    let mut restored = vec![T::default(); sorted.len()];
+    for (item, position) in sorted.into_iter().zip(positions) {
+        restored[*position] = item;
+    }
+    restored
+}
@@ -0,0 +1,56 @@
+// SPDX-FileCopyrightText: 2021 Andrew 'glyph' Reid
+//
+// SPDX-License-Identifier: LGPL-3.0-only
+
+//! Signature verification and hashing for the binary feed formats, which are not covered by
+//! `ssb_verify_signatures`.
+
+use blake2::Blake2b;
+use sha2::{Digest, Sha256};
+use ssb_crypto::{AsBytes, NetworkKey, PublicKey, Signature};
+
+/// Verify a detached ed25519 signature of `data` by `public_key`. With an HMAC key, the
+/// signature is of the HMAC-SHA-512-256 of `data` (as for classic messages).
+pub fn verify_detached(
+    public_key: &[u8],
+    signature: &[u8],
+    data: &[u8],
+    hmac_key: Option<&[u8]>,
+) -> Result<(), String> {
+    let public_key =
+        PublicKey::from_slice(public_key).ok_or_else(|| "public key invalid".to_string())?;
+    let signature =
+        Signature::from_slice(signature).ok_or_else(|| "signature invalid".to_string())?;
+    let verified = match hmac_key {
+        Some(hmac_key) => {
+            let hmac_key = NetworkKey::from_slice(hmac_key)
+                .ok_or_else(|| "hmac key invalid: byte length must equal 32".to_string())?;
+            public_key.verify(&signature, hmac_key.authenticate(data).as_bytes())
+        }
+        None => public_key.verify(&signature, data),
+    };
+    if verified {
+        Ok(())
+    } else {
+        Err("Signature was invalid".to_string())
+    }
+}
+
+/// The SHA-256 hash of `data`.
+pub fn sha256(data: &[u8]) -> [u8; 32] {
+    let mut hash = [0; 32];
+    hash.copy_from_slice(&Sha256::digest(data));
+    hash
+}
+
+/// The BLAKE3 hash of `data`.
+pub fn blake3(data: &[u8]) -> [u8; 32] {
+    *blake3::hash(data).as_bytes()
+}
+
+/// The BLAKE2b-512 hash of `data`.
+pub fn blake2b(data: &[u8]) -> [u8; 64] {
+    let mut hash = [0; 64];
+    hash.copy_from_slice(&Blake2b::digest(data));
+    hash
+}
@@ -0,0 +1,60 @@
+// SPDX-FileCopyrightText: 2021 Andrew 'glyph' Reid
+//
+// SPDX-License-Identifier: LGPL-3.0-only
+
+use std::collections::HashMap;
+
+use crate::message;
+
+/// A batch of messages with exact duplicates (messages with the same key) removed.
+pub struct Deduplicated {
+    /// Unique messages, in the order they were first seen.
+    pub msgs: Vec<Vec<u8>>,
+    /// Keys of the unique messages.
+    pub keys: Vec<String>,
+    /// Index into `msgs` for every position of the original batch.
+    pub positions: Vec<usize>,
+    /// Positions of the original batch holding a copy of an earlier message.
+    pub duplicates: Vec<usize>,
+}
+
+impl Deduplicated {
+    /// Keys for every position of the original batch (duplicates included).
+    pub fn keys_by_position(&self) -> Vec<String> {
+        self.positions
+            .iter()
+            .map(|idx| self.keys[*idx].clone())
+            .collect()
+    }
+}
+
+/// Remove exact duplicates from a batch of messages, so that each message is only verified and
+/// validated once.
+pub fn deduplicate(batch: Vec<Vec<u8>>) -> Deduplicated {
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    let mut deduplicated = Deduplicated {
+        msgs: Vec::new(),
+        keys: Vec::new(),
+        positions: Vec::with_capacity(batch.len()),
+        duplicates: Vec::new(),
+    };
+
+    for (position, msg) in batch.into_iter().enumerate() {
+        let key = message::key(&msg);
+        match seen.get(&key) {
+            Some(idx) => {
+                deduplicated.positions.push(*idx);
+                deduplicated.duplicates.push(position);
+            }
+            None => {
+                let idx = deduplicated.msgs.len();
+                seen.insert(key.clone(), idx);
+                deduplicated.positions.push(idx);
+                deduplicated.msgs.push(msg);
+                deduplicated.keys.push(key);
+            }
+        }
+    }
+
+    deduplicated
+}
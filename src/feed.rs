@@ -0,0 +1,76 @@
+// SPDX-FileCopyrightText: 2021 Andrew 'glyph' Reid
+//
+// SPDX-License-Identifier: LGPL-3.0-only
+
+//! Validation shared by the binary feed formats (Bendy Butt, buttwoo and Gabby Grove): each
+//! message is decoded and verified on its own, then checked against the previous message of its
+//! feed when the batch is ordered.
+
+use std::fmt::Display;
+
+/// A message of a binary feed format, decoded and verified without reference to the rest of its
+/// feed.
+pub trait BinaryMsg: Sized {
+    /// Decode a message and verify its signature (and any other check which needs no other
+    /// message of the feed).
+    fn verify(msg: &[u8], hmac_key: Option<&[u8]>) -> Result<Self, String>;
+
+    /// Check that the message correctly extends a feed, given the previous message of that feed
+    /// (if any).
+    fn validate_link(&self, previous: Option<&Self>) -> Result<(), String>;
+
+    /// The ID of the author, in the form of its feed format.
+    fn author_id(&self) -> String;
+
+    fn sequence(&self) -> u64;
+
+    /// The key of the message, in the form of its feed format.
+    fn key(&self) -> String;
+}
+
+/// Unwrap a decoded field of a message, which is malformed if missing.
+pub fn field<T>(value: Option<T>, name: &str) -> Result<T, String> {
+    value.ok_or_else(|| format!("message invalid: malformed {}", name))
+}
+
+/// Format the error for an invalid message, which is included base64 encoded.
+pub fn invalid(e: impl Display, msg: &[u8]) -> String {
+    format!("found invalid message: {}: {}", e, base64::encode(msg))
+}
+
+/// Verify and validate an ordered run of a single feed, starting from the given previous
+/// message (or the start of the feed). Returns the key of each message.
+pub fn validate_batch<M: BinaryMsg>(
+    msgs: &[Vec<u8>],
+    previous: Option<&[u8]>,
+    hmac_key: Option<&[u8]>,
+) -> Result<Vec<String>, String> {
+    let mut previous = match previous {
+        Some(previous) => Some(M::verify(previous, hmac_key).map_err(|e| invalid(e, previous))?),
+        None => None,
+    };
+    let mut keys = Vec::with_capacity(msgs.len());
+    for msg_bytes in msgs {
+        let msg = M::verify(msg_bytes, hmac_key).map_err(|e| invalid(e, msg_bytes))?;
+        msg.validate_link(previous.as_ref())
+            .map_err(|e| invalid(e, msg_bytes))?;
+        keys.push(msg.key());
+        previous = Some(msg);
+    }
+    Ok(keys)
+}
+
+/// Verify and validate messages of any number of feeds in any order, each without reference to
+/// the others. Returns the key of each message.
+pub fn validate_ooo_batch<M: BinaryMsg>(
+    msgs: &[Vec<u8>],
+    hmac_key: Option<&[u8]>,
+) -> Result<Vec<String>, String> {
+    msgs.iter()
+        .map(|msg_bytes| {
+            M::verify(msg_bytes, hmac_key)
+                .map(|msg| msg.key())
+                .map_err(|e| invalid(e, msg_bytes))
+        })
+        .collect()
+}
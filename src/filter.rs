@@ -0,0 +1,75 @@
+// SPDX-FileCopyrightText: 2021 Andrew 'glyph' Reid
+//
+// SPDX-License-Identifier: LGPL-3.0-only
+
+use std::collections::HashSet;
+
+use serde::Deserialize;
+
+use crate::message;
+
+/// Caller-supplied sets of allowed and blocked feed IDs.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AuthorFilter {
+    /// Feed IDs whose messages are accepted (all feeds if `None`).
+    pub allow: Option<HashSet<String>>,
+    /// Feed IDs whose messages are never accepted.
+    pub block: Option<HashSet<String>>,
+    /// Whether messages from disallowed authors are skipped rather than rejected.
+    #[serde(default)]
+    pub skip: bool,
+}
+
+/// A batch of messages with the messages of disallowed authors removed.
+pub struct Filtered {
+    /// Messages of allowed authors, in input order.
+    pub msgs: Vec<Vec<u8>>,
+    /// Position in the original batch of each message in `msgs`.
+    pub kept: Vec<usize>,
+    /// Positions of the original batch holding a message of a disallowed author.
+    pub skipped: Vec<usize>,
+}
+
+impl AuthorFilter {
+    fn allows(&self, author: &str) -> bool {
+        let allowed = match &self.allow {
+            Some(allow) => allow.contains(author),
+            None => true,
+        };
+        let blocked = match &self.block {
+            Some(block) => block.contains(author),
+            None => false,
+        };
+        allowed && !blocked
+    }
+
+    /// Remove (if `skip` is set) or reject the messages of disallowed authors. Only the author
+    /// of each message is read, so this is cheap compared to signature verification.
+    pub fn apply(&self, batch: Vec<Vec<u8>>) -> Result<Filtered, String> {
+        let mut filtered = Filtered {
+            msgs: Vec::with_capacity(batch.len()),
+            kept: Vec::with_capacity(batch.len()),
+            skipped: Vec::new(),
+        };
+
+        for (position, msg) in batch.into_iter().enumerate() {
+            let meta = message::parse_meta(&msg)
+                .map_err(|e| format!("found invalid message: {}: {}", e, message::as_str(&msg)))?;
+            if self.allows(&meta.author) {
+                filtered.msgs.push(msg);
+                filtered.kept.push(position);
+            } else if self.skip {
+                filtered.skipped.push(position);
+            } else {
+                return Err(format!(
+                    "found invalid message: author not allowed: {}: {}",
+                    meta.author,
+                    message::as_str(&msg)
+                ));
+            }
+        }
+
+        Ok(filtered)
+    }
+}
@@ -0,0 +1,94 @@
+// SPDX-FileCopyrightText: 2021 Andrew 'glyph' Reid
+//
+// SPDX-License-Identifier: LGPL-3.0-only
+
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{message, state::FeedTip};
+
+/// What a batch does with the forks it finds.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ForkPolicy {
+    /// Fail with a `fork detected` error describing the first fork.
+    Error,
+    /// Report every fork (as `forks`) along with the keys.
+    Report,
+    /// Leave forks unchecked, each message being validated on its own.
+    Ignore,
+}
+
+/// Two different messages by the same author with the same sequence number.
+#[derive(Debug, Serialize)]
+pub struct Fork {
+    pub author: String,
+    pub sequence: u64,
+    pub keys: [String; 2],
+}
+
+impl fmt::Display for Fork {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "fork detected: {} has two messages with sequence {}: {} and {}",
+            self.author, self.sequence, self.keys[0], self.keys[1]
+        )
+    }
+}
+
+/// Find every fork within a batch of messages, or between a message in the batch and a known
+/// feed tip.
+///
+/// Exact duplicates (messages with the same key) are not forks. Each conflicting message is
+/// reported against the first message seen at its position, with known tips taking precedence
+/// over messages in the batch.
+pub fn detect_forks(msgs: &[Vec<u8>], tips: &[FeedTip]) -> Result<Vec<Fork>, String> {
+    let positions = msgs
+        .iter()
+        .map(|msg| {
+            let meta = message::parse_meta(msg)
+                .map_err(|e| format!("found invalid message: {}: {}", e, message::as_str(msg)))?;
+            Ok(FeedTip {
+                author: meta.author,
+                sequence: meta.sequence,
+                key: message::key(msg),
+            })
+        })
+        .collect::<Result<Vec<FeedTip>, String>>()?;
+    Ok(find_forks(positions, tips))
+}
+
+/// Find every fork among the positions (author, sequence and key) of a batch of messages of any
+/// feed format, or between one of them and a known feed tip (see `detect_forks`).
+pub fn find_forks(positions: Vec<FeedTip>, tips: &[FeedTip]) -> Vec<Fork> {
+    let mut seen: HashMap<(String, u64), String> = tips
+        .iter()
+        .map(|tip| ((tip.author.clone(), tip.sequence), tip.key.clone()))
+        .collect();
+
+    let mut forks = Vec::new();
+    for FeedTip {
+        author,
+        sequence,
+        key,
+    } in positions
+    {
+        let position = (author, sequence);
+        match seen.get(&position) {
+            Some(seen_key) if *seen_key != key => forks.push(Fork {
+                author: position.0.clone(),
+                sequence: position.1,
+                keys: [seen_key.clone(), key],
+            }),
+            Some(_) => (),
+            None => {
+                seen.insert(position, key);
+            }
+        }
+    }
+
+    forks
+}
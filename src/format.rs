@@ -0,0 +1,213 @@
+// SPDX-FileCopyrightText: 2021 Andrew 'glyph' Reid
+//
+// SPDX-License-Identifier: LGPL-3.0-only
+
+//! The registry of feed formats, for batches which mix messages of different formats.
+//!
+//! Classic messages are passed in as JSON message values, as everywhere else, and must have a
+//! classic (`.ed25519`) author. Messages of the binary formats are passed in as JSON strings of
+//! their base64 encoding: the first byte of the encoding picks the decoder, and the author of the
+//! decoded message, whose format is given by the suffix (or URI prefix) of its ID, must be of the
+//! format it was decoded as.
+
+use ssb_validate::message_value::validate_message_value;
+use ssb_verify_signatures::verify_message_value;
+
+use crate::{
+    bendybutt::BendyButtMsg,
+    buttwoo::ButtwooMsg,
+    feed::{self, BinaryMsg},
+    gabbygrove::GabbyGroveMsg,
+    message,
+};
+
+/// A feed format which messages can be validated in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FeedFormat {
+    /// JSON messages with `@...=.ed25519` authors.
+    Classic,
+    /// Bencoded metafeed messages with `@...=.bbfeed-v1` authors.
+    BendyButt,
+    /// BIPF encoded messages with `ssb:feed/buttwoo-v1/...` authors.
+    Buttwoo,
+    /// CBOR encoded events with `@...=.ggfeed-v1` authors.
+    GabbyGrove,
+}
+
+/// The author ID suffix of each format with `@...=.suffix` IDs.
+const SUFFIXES: [(&str, FeedFormat); 3] = [
+    (".ed25519", FeedFormat::Classic),
+    (".bbfeed-v1", FeedFormat::BendyButt),
+    (".ggfeed-v1", FeedFormat::GabbyGrove),
+];
+
+/// The author ID prefix of each format with `ssb:feed/...` URIs.
+const URI_PREFIXES: [(&str, FeedFormat); 1] = [("ssb:feed/buttwoo-v1/", FeedFormat::Buttwoo)];
+
+/// A message of any format, as passed in.
+pub enum FeedMsg {
+    /// A serialized JSON message value.
+    Value(Vec<u8>),
+    /// An encoded message of a binary format.
+    Binary(Vec<u8>),
+}
+
+/// The author, sequence number and key of a validated message of any format.
+pub struct ValidatedMsg {
+    pub author: String,
+    pub sequence: u64,
+    pub key: String,
+}
+
+impl FeedFormat {
+    pub fn name(self) -> &'static str {
+        match self {
+            FeedFormat::Classic => "classic",
+            FeedFormat::BendyButt => "bendybutt-v1",
+            FeedFormat::Buttwoo => "buttwoo-v1",
+            FeedFormat::GabbyGrove => "gabbygrove-v1",
+        }
+    }
+
+    /// The format of the feed with the given author ID.
+    pub fn from_author(author: &str) -> Result<FeedFormat, String> {
+        let by_suffix = SUFFIXES
+            .iter()
+            .find(|(suffix, _)| author.starts_with('@') && author.ends_with(suffix));
+        let by_prefix = URI_PREFIXES
+            .iter()
+            .find(|(prefix, _)| author.starts_with(prefix));
+        by_suffix
+            .or(by_prefix)
+            .map(|(_, format)| *format)
+            .ok_or_else(|| format!("unsupported feed format: {}", author))
+    }
+
+    /// The format of a binary message, told apart by its encoding: a bencoded list (Bendy
+    /// Butt), a CBOR array of three items (Gabby Grove) or a BIPF array (buttwoo).
+    fn from_encoding(msg: &[u8]) -> Option<FeedFormat> {
+        match msg.first() {
+            Some(b'l') => Some(FeedFormat::BendyButt),
+            Some(0x83) => Some(FeedFormat::GabbyGrove),
+            Some(tag) if tag & 7 == 4 => Some(FeedFormat::Buttwoo),
+            _ => None,
+        }
+    }
+
+    /// Verify and validate a message of this format without reference to any other message of
+    /// its feed, as in an out-of-order batch.
+    fn validate_ooo(self, msg: &[u8], hmac_key: Option<&[u8]>) -> Result<ValidatedMsg, String> {
+        match self {
+            FeedFormat::Classic => {
+                let invalid =
+                    |e: String| format!("found invalid message: {}: {}", e, message::as_str(msg));
+                verify_message_value(msg, hmac_key).map_err(|e| invalid(e.to_string()))?;
+                validate_message_value(msg).map_err(|e| invalid(e.to_string()))?;
+                let meta = message::parse_meta(msg).map_err(invalid)?;
+                Ok(ValidatedMsg {
+                    author: meta.author,
+                    sequence: meta.sequence,
+                    key: message::key(msg),
+                })
+            }
+            FeedFormat::BendyButt => validate_binary::<BendyButtMsg>(msg, hmac_key),
+            FeedFormat::Buttwoo => validate_binary::<ButtwooMsg>(msg, hmac_key),
+            FeedFormat::GabbyGrove => validate_binary::<GabbyGroveMsg>(msg, hmac_key),
+        }
+    }
+}
+
+/// Verify a message of a binary format without reference to any other message of its feed.
+fn validate_binary<M: BinaryMsg>(
+    msg: &[u8],
+    hmac_key: Option<&[u8]>,
+) -> Result<ValidatedMsg, String> {
+    M::verify(msg, hmac_key)
+        .map(|msg| ValidatedMsg {
+            author: msg.author_id(),
+            sequence: msg.sequence(),
+            key: msg.key(),
+        })
+        .map_err(|e| feed::invalid(e, msg))
+}
+
+/// Whether a batch holds no binary messages, so that it can be validated as before. Only the
+/// first byte of each message is looked at (a binary message is a JSON string); a message value
+/// by an author of another format is rejected by the validation of classic messages.
+pub fn is_classic_batch(msgs: &[Vec<u8>]) -> bool {
+    !msgs.iter().any(|msg| is_json_string(msg))
+}
+
+fn is_json_string(msg: &[u8]) -> bool {
+    msg.iter().find(|byte| !byte.is_ascii_whitespace()) == Some(&b'"')
+}
+
+impl FeedMsg {
+    /// Tell a JSON message value apart from the base64 JSON string of a binary message.
+    pub fn from_element(element: Vec<u8>) -> Result<FeedMsg, String> {
+        if !is_json_string(&element) {
+            return Ok(FeedMsg::Value(element));
+        }
+        serde_json::from_slice::<String>(&element)
+            .ok()
+            .and_then(|encoded| base64::decode(encoded).ok())
+            .map(FeedMsg::Binary)
+            .ok_or_else(|| {
+                format!(
+                    "found invalid message: must be base64 encoded: {}",
+                    message::as_str(&element)
+                )
+            })
+    }
+
+    fn bytes(&self) -> &[u8] {
+        match self {
+            FeedMsg::Value(msg) | FeedMsg::Binary(msg) => msg,
+        }
+    }
+
+    /// The feed format of a message. The author of a message value is read from its `author`
+    /// field, and a message value by an author of a binary format is rejected. The format of a
+    /// binary message is picked by the first byte of its encoding (see `from_encoding`).
+    pub fn format(&self) -> Result<FeedFormat, String> {
+        match self {
+            FeedMsg::Value(msg) => {
+                let invalid = |e| format!("found invalid message: {}: {}", e, message::as_str(msg));
+                let meta = message::parse_meta(msg).map_err(invalid)?;
+                match FeedFormat::from_author(&meta.author).map_err(invalid)? {
+                    FeedFormat::Classic => Ok(FeedFormat::Classic),
+                    format => Err(invalid(format!(
+                        "{} messages must be passed in their binary encoding",
+                        format.name()
+                    ))),
+                }
+            }
+            FeedMsg::Binary(msg) => FeedFormat::from_encoding(msg).ok_or_else(|| {
+                format!(
+                    "found invalid message: unsupported feed format: unknown encoding: {}",
+                    base64::encode(msg)
+                )
+            }),
+        }
+    }
+
+    /// Verify and validate the message in the given format (see `format`), without reference
+    /// to any other message of its feed. A message whose decoded author is not of that format
+    /// is rejected, so that a message is only accepted by the format of its author.
+    pub fn validate_ooo(
+        &self,
+        format: FeedFormat,
+        hmac_key: Option<&[u8]>,
+    ) -> Result<ValidatedMsg, String> {
+        let validated = format.validate_ooo(self.bytes(), hmac_key)?;
+        if FeedFormat::from_author(&validated.author) != Ok(format) {
+            return Err(format!(
+                "found invalid message: author {} is not of the {} format: {}",
+                validated.author,
+                format.name(),
+                base64::encode(self.bytes())
+            ));
+        }
+        Ok(validated)
+    }
+}
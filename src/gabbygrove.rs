@@ -0,0 +1,214 @@
+// SPDX-FileCopyrightText: 2021 Andrew 'glyph' Reid
+//
+// SPDX-License-Identifier: LGPL-3.0-only
+
+//! Validation of Gabby Grove events: a CBOR encoded feed format with `.ggfeed-v1` feed IDs and
+//! `.ggmsg-v1` message keys, which transfers the content separately from the signed event.
+//!
+//! A message is the CBOR array `[event, signature, content]`, where `event` is itself the CBOR
+//! array `[previous, author, sequence, timestamp, [contentHash, contentSize, contentType]]`.
+//! References are byte strings of a type byte followed by a 32 byte key or hash, tagged with
+//! `REF_TAG`. The signature is made over the encoded event and the key of the message is the
+//! SHA-256 hash of the encoded event followed by the signature.
+
+use crate::{
+    cbor::{self, Value},
+    crypto,
+    feed::{field, BinaryMsg},
+};
+
+/// The maximum byte length of a transferred event, signature and content.
+const MAX_MESSAGE_BYTES: usize = 16384;
+
+/// The CBOR tag of a reference.
+const REF_TAG: u64 = 1050;
+
+/// Type byte of a feed reference (an ed25519 public key).
+const REF_FEED: u8 = 0x01;
+/// Type byte of a message reference.
+const REF_MESSAGE: u8 = 0x02;
+/// Type byte of a content hash (SHA-256).
+const REF_CONTENT: u8 = 0x03;
+
+/// The content types: JSON and CBOR.
+const CONTENT_TYPES: [i128; 2] = [1, 2];
+
+/// The fields of a verified Gabby Grove event.
+pub struct GabbyGroveMsg {
+    /// Public key of the author.
+    pub author: Vec<u8>,
+    pub sequence: u64,
+    /// Hash of the previous event (`None` for the first event of a feed).
+    pub previous: Option<Vec<u8>>,
+    /// Hash of the event.
+    pub hash: [u8; 32],
+}
+
+impl BinaryMsg for GabbyGroveMsg {
+    fn verify(msg: &[u8], hmac_key: Option<&[u8]>) -> Result<GabbyGroveMsg, String> {
+        verify(msg, hmac_key)
+    }
+
+    fn validate_link(&self, previous: Option<&GabbyGroveMsg>) -> Result<(), String> {
+        validate_link(self, previous)
+    }
+
+    /// The `@...=.ggfeed-v1` ID of the author.
+    fn author_id(&self) -> String {
+        format!("@{}.ggfeed-v1", base64::encode(&self.author))
+    }
+
+    fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    /// The `%...=.ggmsg-v1` key of the event.
+    fn key(&self) -> String {
+        format!("%{}.ggmsg-v1", base64::encode(self.hash))
+    }
+}
+
+/// Decode a reference of the given type, returning its 32 byte key or hash.
+fn decode_ref<'a>(value: &'a Value, ref_type: u8, name: &str) -> Result<&'a [u8], String> {
+    match field(value.as_tagged(REF_TAG).and_then(Value::as_bytes), name)? {
+        [value_type, data @ ..] if *value_type == ref_type && data.len() == 32 => Ok(data),
+        _ => Err(format!(
+            "{} invalid: unexpected reference type or length",
+            name
+        )),
+    }
+}
+
+/// Decode a message and verify its signature and content hash, without reference to any other
+/// event of the feed.
+pub fn verify(msg: &[u8], hmac_key: Option<&[u8]>) -> Result<GabbyGroveMsg, String> {
+    if msg.len() > MAX_MESSAGE_BYTES {
+        return Err(format!(
+            "message invalid: byte length ({}) exceeds the maximum of {}",
+            msg.len(),
+            MAX_MESSAGE_BYTES
+        ));
+    }
+
+    let (encoded_event, signature, content) = match &cbor::decode(msg)? {
+        Value::Array(parts) => match &parts[..] {
+            [Value::Bytes(event), Value::Bytes(signature), content] => {
+                (event.clone(), signature.clone(), content.clone())
+            }
+            _ => {
+                return Err(
+                    "message invalid: must be an array of event, signature and content".to_string(),
+                )
+            }
+        },
+        _ => return Err("message invalid: must be an array".to_string()),
+    };
+
+    let event = cbor::decode(&encoded_event)?;
+    let (previous, author, sequence, timestamp, content_meta) =
+        match field(event.as_array(), "event")? {
+            [previous, author, sequence, timestamp, content_meta] => {
+                (previous, author, sequence, timestamp, content_meta)
+            }
+            _ => return Err("message invalid: event must be an array of five fields".to_string()),
+        };
+
+    let author = decode_ref(author, REF_FEED, "author")?;
+    let sequence = field(sequence.as_int().filter(|seq| *seq >= 1), "sequence")?;
+    if sequence > i128::from(u64::MAX) {
+        return Err("message invalid: malformed sequence".to_string());
+    }
+    let sequence = sequence as u64;
+    let previous = match (sequence, previous) {
+        (1, Value::Null) => None,
+        (1, _) => {
+            return Err("The first message of a feed must have previous of null".to_string());
+        }
+        (_, previous) => Some(decode_ref(previous, REF_MESSAGE, "previous")?.to_vec()),
+    };
+    field(timestamp.as_int(), "timestamp")?;
+    let (content_hash, content_size, content_type) =
+        match field(content_meta.as_array(), "content")? {
+            [hash, size, content_type] => (hash, size, content_type),
+            _ => {
+                return Err("message invalid: content must be an array of three fields".to_string())
+            }
+        };
+    let content_hash = decode_ref(content_hash, REF_CONTENT, "content hash")?;
+    let content_size = field(
+        content_size
+            .as_int()
+            .filter(|size| *size >= 0 && *size <= i128::from(u16::MAX)),
+        "content size",
+    )? as usize;
+    match content_type.as_int() {
+        Some(content_type) if CONTENT_TYPES.contains(&content_type) => (),
+        _ => return Err("content invalid: unknown content type".to_string()),
+    }
+
+    if signature.len() != 64 {
+        return Err("signature invalid: must be 64 bytes".to_string());
+    }
+    crypto::verify_detached(author, &signature, &encoded_event, hmac_key)?;
+
+    // the content is transferred separately and may be missing
+    match &content {
+        Value::Null => (),
+        Value::Bytes(content) => {
+            if content.len() != content_size {
+                return Err(format!(
+                    "content invalid: byte length ({}) does not match content size ({})",
+                    content.len(),
+                    content_size
+                ));
+            }
+            if content_hash != crypto::sha256(content) {
+                return Err("content invalid: does not match content hash".to_string());
+            }
+        }
+        _ => return Err("content invalid: must be bytes or null".to_string()),
+    }
+
+    let mut signed = encoded_event.clone();
+    signed.extend_from_slice(&signature);
+    Ok(GabbyGroveMsg {
+        author: author.to_vec(),
+        sequence,
+        previous,
+        hash: crypto::sha256(&signed),
+    })
+}
+
+/// Check that a verified event correctly extends a feed, given the previous event of that feed
+/// (if any).
+pub fn validate_link(msg: &GabbyGroveMsg, previous: Option<&GabbyGroveMsg>) -> Result<(), String> {
+    match previous {
+        None => {
+            if msg.sequence != 1 {
+                return Err("The first message of a feed must have seq of 1".to_string());
+            }
+        }
+        Some(previous) => {
+            if msg.author != previous.author {
+                return Err(format!(
+                    "author of message ({}) did not match author of previous message ({})",
+                    msg.author_id(),
+                    previous.author_id()
+                ));
+            }
+            if previous.sequence.checked_add(1) != Some(msg.sequence) {
+                return Err(format!(
+                    "sequence of message ({}) is not one greater than that of previous message ({})",
+                    msg.sequence, previous.sequence
+                ));
+            }
+            if msg.previous.as_deref() != Some(&previous.hash[..]) {
+                return Err(format!(
+                    "previous of message did not match key of previous message ({})",
+                    previous.key()
+                ));
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,59 @@
+// SPDX-FileCopyrightText: 2021 Andrew 'glyph' Reid
+//
+// SPDX-License-Identifier: LGPL-3.0-only
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::Serialize;
+
+use crate::message;
+
+/// Sequence coverage of one feed within a batch of messages.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeedGaps {
+    pub author: String,
+    pub min_sequence: u64,
+    pub max_sequence: u64,
+    /// Inclusive `[start, end]` ranges of sequence numbers between `min_sequence` and
+    /// `max_sequence` which are not present in the batch.
+    pub missing: Vec<[u64; 2]>,
+}
+
+/// List the sequence range seen for each author in a batch, along with the ranges missing
+/// within it. Feeds are sorted by author.
+pub fn find_gaps(msgs: &[Vec<u8>]) -> Result<Vec<FeedGaps>, String> {
+    let mut sequences: BTreeMap<String, BTreeSet<u64>> = BTreeMap::new();
+    for msg in msgs {
+        let meta = message::parse_meta(msg)
+            .map_err(|e| format!("found invalid message: {}: {}", e, message::as_str(msg)))?;
+        sequences
+            .entry(meta.author)
+            .or_default()
+            .insert(meta.sequence);
+    }
+
+    let feeds = sequences
+        .into_iter()
+        .filter_map(|(author, seqs)| {
+            let min_sequence = *seqs.iter().next()?;
+            let max_sequence = *seqs.iter().next_back()?;
+            let mut missing = Vec::new();
+            let mut expected = min_sequence;
+            for seq in seqs {
+                if seq > expected {
+                    missing.push([expected, seq - 1]);
+                }
+                expected = seq + 1;
+            }
+            Some(FeedGaps {
+                author,
+                min_sequence,
+                max_sequence,
+                missing,
+            })
+        })
+        .collect();
+
+    Ok(feeds)
+}
@@ -0,0 +1,204 @@
+// SPDX-FileCopyrightText: 2021 Andrew 'glyph' Reid
+//
+// SPDX-License-Identifier: LGPL-3.0-only
+
+use std::collections::HashMap;
+
+use js_sys::{Array, Object, Reflect};
+use ssb_crypto::{AsBytes, NetworkKey as MsgHmacKey};
+use ssb_verify_signatures::verify_message_value;
+use wasm_bindgen::prelude::*;
+
+use crate::message;
+
+const INVALID_KEY: &str =
+    "hmac key invalid: must be null, undefined, string, buffer, an array of these or a map of authors to these";
+
+/// An HMAC key (or keys), decoded and validated once when it is created and reusable across
+/// calls. Every function which verifies signatures takes one in place of the raw key.
+#[wasm_bindgen]
+pub struct HmacKey {
+    keys: HmacKeys,
+}
+
+#[wasm_bindgen]
+impl HmacKey {
+    /// Create an HMAC key from any of the forms taken by the functions: `null` or `undefined`
+    /// (no HMAC key), a base64 string, a buffer, an array of these or a `{ authors, default }`
+    /// map of authors to these. Throws if the key is invalid.
+    #[wasm_bindgen(constructor)]
+    pub fn new(value: JsValue) -> Result<HmacKey, JsValue> {
+        HmacKeys::from_js(&value)
+            .map(|keys| HmacKey { keys })
+            .map_err(|err_msg| JsValue::from_str(&err_msg))
+    }
+
+    /// Create an HMAC key from its base64 encoding. Throws if the key is invalid.
+    #[wasm_bindgen(js_name = fromBase64)]
+    pub fn from_base64(encoded: &str) -> Result<HmacKey, JsValue> {
+        decode_base64(encoded)
+            .and_then(|bytes| is_valid_hmac_key(Some(bytes)))
+            .map(|key| HmacKey {
+                keys: HmacKeys::Single(key),
+            })
+            .map_err(|err_msg| JsValue::from_str(&err_msg))
+    }
+
+    /// Create an HMAC key from its 32 bytes. Throws if the key is invalid.
+    #[wasm_bindgen(js_name = fromBytes)]
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<HmacKey, JsValue> {
+        is_valid_hmac_key(Some(bytes))
+            .map(|key| HmacKey {
+                keys: HmacKeys::Single(key),
+            })
+            .map_err(|err_msg| JsValue::from_str(&err_msg))
+    }
+}
+
+impl HmacKey {
+    /// The key (or keys) to verify signatures with.
+    pub fn keys(&self) -> &HmacKeys {
+        &self.keys
+    }
+
+    /// The key for functions which take a single key only (`None` to verify without an HMAC
+    /// key).
+    pub fn single(&self) -> Result<Option<&[u8]>, String> {
+        match &self.keys {
+            HmacKeys::Single(hmac_key) => Ok(hmac_key.as_deref()),
+            _ => Err("hmac key invalid: must be a single key for this function".to_string()),
+        }
+    }
+}
+
+fn is_valid_hmac_key(hmac_key: Option<Vec<u8>>) -> Result<Option<Vec<u8>>, String> {
+    match hmac_key {
+        Some(hmac) => {
+            let key = MsgHmacKey::from_slice(&hmac);
+            match key {
+                None => Err("hmac key invalid: byte length must equal 32".to_string()),
+                Some(key_val) => {
+                    let key_bytes = key_val.as_bytes().to_vec();
+                    Ok(Some(key_bytes))
+                }
+            }
+        }
+        None => Ok(None),
+    }
+}
+
+/// Decode a base64 string, which must be in its canonical (padded) form.
+fn decode_base64(encoded: &str) -> Result<Vec<u8>, String> {
+    match base64::decode(encoded) {
+        Ok(bytes) if base64::encode(&bytes) == encoded => Ok(bytes),
+        _ => Err("hmac key invalid: string must be base64 encoded".to_string()),
+    }
+}
+
+/// Decode and validate a single HMAC key passed in from JS: `null` or `undefined` (`None`), a
+/// base64 string or a buffer.
+fn parse_key(value: &JsValue) -> Result<Option<Vec<u8>>, String> {
+    if value.is_null() || value.is_undefined() {
+        return Ok(None);
+    }
+    let bytes = match value.as_string() {
+        Some(encoded) => decode_base64(&encoded)?,
+        None => {
+            serde_wasm_bindgen::from_value(value.clone()).map_err(|_| INVALID_KEY.to_string())?
+        }
+    };
+    is_valid_hmac_key(Some(bytes))
+}
+
+/// The HMAC key (or keys) to verify message signatures with.
+pub enum HmacKeys {
+    /// A single key (`None` to verify without an HMAC key).
+    Single(Option<Vec<u8>>),
+    /// A list of keys to try in order (a `None` entry to try without an HMAC key).
+    List(Vec<Option<Vec<u8>>>),
+    /// A key for each author, with a default for authors not in the map.
+    PerAuthor {
+        authors: HashMap<String, Option<Vec<u8>>>,
+        default: Option<Vec<u8>>,
+    },
+}
+
+impl HmacKeys {
+    /// Decode and validate an HMAC key, an array of HMAC keys or a `{ authors, default }` map
+    /// from author ID to HMAC key, passed in from JS.
+    fn from_js(value: &JsValue) -> Result<HmacKeys, String> {
+        if Array::is_array(value) {
+            let hmac_keys = Array::from(value)
+                .iter()
+                .map(|hmac_key| parse_key(&hmac_key))
+                .collect::<Result<Vec<_>, _>>()?;
+            if hmac_keys.is_empty() {
+                return Err("hmac key invalid: array of keys must not be empty".to_string());
+            }
+            return Ok(HmacKeys::List(hmac_keys));
+        }
+        let authors =
+            Reflect::get(value, &JsValue::from_str("authors")).unwrap_or(JsValue::UNDEFINED);
+        if !value.is_object() || authors.is_undefined() {
+            return parse_key(value).map(HmacKeys::Single);
+        }
+        // the map takes no fields other than `authors` and `default`
+        let fields = Object::keys(&Object::from(value.clone()));
+        if fields
+            .iter()
+            .any(|field| field != "authors" && field != "default")
+            || !authors.is_object()
+        {
+            return Err(INVALID_KEY.to_string());
+        }
+        let authors = Object::entries(&Object::from(authors))
+            .iter()
+            .map(|entry| {
+                let entry = Array::from(&entry);
+                let author = entry.get(0).as_string().unwrap_or_default();
+                Ok((author, parse_key(&entry.get(1))?))
+            })
+            .collect::<Result<_, String>>()?;
+        let default =
+            Reflect::get(value, &JsValue::from_str("default")).unwrap_or(JsValue::UNDEFINED);
+        Ok(HmacKeys::PerAuthor {
+            authors,
+            default: parse_key(&default)?,
+        })
+    }
+
+    /// Whether a list of keys was given, in which case the matched key of each message is
+    /// reported.
+    pub fn is_list(&self) -> bool {
+        matches!(self, HmacKeys::List(_))
+    }
+
+    /// Verify the signature of a message, returning the index of the key it verified with.
+    /// A single key (or the key of the author) always has index 0.
+    pub fn verify(&self, msg: &[u8]) -> Result<usize, String> {
+        match self {
+            HmacKeys::Single(hmac_key) => verify_message_value(msg, hmac_key.as_deref())
+                .map(|_| 0)
+                .map_err(|e| format!("found invalid message: {}: {}", e, message::as_str(msg))),
+            HmacKeys::List(hmac_keys) => hmac_keys
+                .iter()
+                .position(|hmac_key| verify_message_value(msg, hmac_key.as_deref()).is_ok())
+                .ok_or_else(|| {
+                    format!(
+                        "found invalid message: signature did not verify with any of the {} hmac keys: {}",
+                        hmac_keys.len(),
+                        message::as_str(msg)
+                    )
+                }),
+            HmacKeys::PerAuthor { authors, default } => {
+                let meta = message::parse_meta(msg).map_err(|e| {
+                    format!("found invalid message: {}: {}", e, message::as_str(msg))
+                })?;
+                let hmac_key = authors.get(&meta.author).unwrap_or(default);
+                verify_message_value(msg, hmac_key.as_deref())
+                    .map(|_| 0)
+                    .map_err(|e| format!("found invalid message: {}: {}", e, message::as_str(msg)))
+            }
+        }
+    }
+}
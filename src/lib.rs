@@ -2,34 +2,51 @@
 //
 // SPDX-License-Identifier: LGPL-3.0-only
 
-use ssb_crypto::{AsBytes, NetworkKey as MsgHmacKey};
-use ssb_validate::{
-    message_value::{
-        par_validate_message_value, par_validate_message_value_hash_chain_of_feed,
-        par_validate_ooo_message_value_hash_chain_of_feed, validate_message_value,
-        validate_message_value_hash_chain, validate_ooo_message_value_hash_chain,
-    },
-    utils,
-};
+use ssb_validate::{message_value::validate_message_value_hash_chain, utils};
 use ssb_verify_signatures::verify_message_value;
 use wasm_bindgen::prelude::*;
 pub use wasm_bindgen_rayon::init_thread_pool;
 
-fn is_valid_hmac_key(hmac_key: Option<Vec<u8>>) -> Result<Option<Vec<u8>>, String> {
-    match hmac_key {
-        Some(hmac) => {
-            let key = MsgHmacKey::from_slice(&hmac);
-            match key {
-                None => Err("hmac key invalid: byte length must equal 32".to_string()),
-                Some(key_val) => {
-                    let key_bytes = key_val.as_bytes().to_vec();
-                    Ok(Some(key_bytes))
-                }
-            }
-        }
-        None => Ok(None),
-    }
-}
+mod bamboo;
+mod batch;
+mod bencode;
+mod bendybutt;
+mod bfe;
+mod bipf;
+mod buttwoo;
+mod cbor;
+mod chain;
+mod crypto;
+mod dedupe;
+mod feed;
+mod filter;
+mod fork;
+mod format;
+mod gabbygrove;
+mod gaps;
+mod hmac;
+mod limits;
+mod message;
+mod metafeed;
+mod options;
+mod reorder;
+mod report;
+mod rules;
+mod state;
+mod timestamp;
+
+pub use hmac::HmacKey;
+pub use reorder::ReorderBuffer;
+pub use state::FeedState;
+
+use bendybutt::BendyButtMsg;
+use buttwoo::ButtwooMsg;
+use feed::BinaryMsg;
+use gabbygrove::GabbyGroveMsg;
+use limits::Limits;
+use options::{Ordering, Output, ValidationOptions};
+use report::BatchReport;
+use state::{FeedTip, Previous};
 
 fn hash(msgs: Vec<Vec<u8>>) -> Vec<String> {
     let mut keys = Vec::new();
@@ -41,80 +58,101 @@ fn hash(msgs: Vec<Vec<u8>>) -> Vec<String> {
     keys
 }
 
+/// Return the response for an error (`[err, null]`).
+fn error_response(err_msg: String) -> JsValue {
+    let response: (Option<String>, Option<()>) = (Some(err_msg), None);
+    // TODO: find a more efficient approach to return the response
+    // see wasm_bindgen docs for info on why this is slow (esp. with large payloads)
+    JsValue::from_serde(&response).expect("failed to serialize response with error")
+}
+
+/// Return the result of a validated batch: the full report if `detailed` is set (ie. any part
+/// of the report was requested), or else the array of keys alone.
+fn report_response(report: BatchReport, detailed: bool) -> JsValue {
+    if detailed {
+        let response: (Option<String>, Option<BatchReport>) = (None, Some(report));
+        JsValue::from_serde(&response).expect("failed to serialize response with batch report")
+    } else {
+        let response: (Option<String>, Option<Vec<String>>) = (None, Some(report.keys));
+        JsValue::from_serde(&response)
+            .expect("failed to serialize response with successfully verified keys")
+    }
+}
+
+/// Return the result of a batch pipeline: the error, or else the report in the requested form
+/// (see `report_response`), where an explicit `output` overrides whether the report is returned.
+fn batch_response(result: batch::BatchResult, output: Option<Output>) -> JsValue {
+    match result {
+        Ok((report, detailed)) => {
+            let detailed = output.map_or(detailed, |output| output == Output::Report);
+            report_response(report, detailed)
+        }
+        Err(err_msg) => error_response(err_msg),
+    }
+}
+
 /// Verify signatures for an array of messages (includes HMAC key support).
 ///
 /// Takes an HMAC key as the first argument and an array of messages as the second argument.
-/// The HMAC key must be of type `ArrayBuffer`. Message signatures are verified without
-/// an HMAC key if the value of the argument is `null` or `undefined` (maps to a `None` value).
+/// The HMAC key is an `HmacKey`; message signatures are verified without an HMAC key if it was
+/// created from `null` or `undefined`.
 ///
 /// If verification fails, the cause of the error is returned along with the offending message.
 /// Note: this method only verifies message signatures; it does not perform full message validation
-/// (use `verify_validate_message_array` for complete verification and validation).
+/// (use `verify_validate_message_array` for complete verification and validation). Forks are not
+/// detected either, since messages which are only verified are not taken to be of one feed (use
+/// `detectForks`).
+///
+/// In place of a single HMAC key, an `HmacKey` of an array of keys (with `null` for no HMAC key)
+/// may be given.
+/// Each message is then verified with the first key in the array that its signature matches,
+/// and successful verification returns `{ keys, matchedKeys }`, where `matchedKeys` holds the
+/// index of the key each message verified with.
+///
+/// Alternatively, an `HmacKey` of a `{ authors, default }` map may be given, where `authors` maps author IDs
+/// to HMAC keys. Each message is then verified with the key of its author, or the `default` key
+/// (`null` for no HMAC key) if the author is not in the map.
 #[wasm_bindgen(js_name = verifySignatures)]
-pub fn verify_messages(hmac_key: JsValue, array: JsValue) -> JsValue {
-    // value will be `None` (input was `null` or `undefined`) or
-    // `Some<Vec<u8>>` (input was an ArrayBuffer)
-    let hmac_key: Option<Vec<u8>> = match serde_wasm_bindgen::from_value(hmac_key) {
-        Ok(hmac) => hmac,
-        Err(_) => {
-            let err_msg = "hmac key invalid: must be null, undefined, string or buffer".to_string();
-            let response: (Option<String>, Option<Vec<String>>) = (Some(err_msg), None);
-            // TODO: find a more efficient approach to return the response
-            // see wasm_bindgen docs for info on why this is slow (esp. with large payloads)
-            return JsValue::from_serde(&response)
-                .expect("failed to serialize response for hmac key value error");
-        }
-    };
+pub fn verify_messages(hmac_key: &HmacKey, array: JsValue) -> JsValue {
+    let hmac_keys = hmac_key.keys();
 
-    let valid_hmac = match is_valid_hmac_key(hmac_key) {
-        Ok(key) => key,
-        Err(err_msg) => {
-            let response: (Option<String>, Option<Vec<String>>) = (Some(err_msg), None);
-            return JsValue::from_serde(&response)
-                .expect("failed to serialize response for invalid hmac key error");
-        }
+    let elements = match limits::read_messages(&array, &Limits::defaults()) {
+        Ok(elements) => elements,
+        Err(err_msg) => return error_response(err_msg),
     };
-    let hmac = valid_hmac.as_deref();
-
-    let elements: Vec<String> = array
-        .into_serde()
-        .expect("failed to deserialize js message array into vector of strings");
     let mut msgs = Vec::new();
     for msg in elements {
         let msg_bytes = msg.into_bytes();
         msgs.push(msg_bytes)
     }
 
+    // attempt verification of each message, remembering the key it verified with
+    let mut matched_keys = Vec::with_capacity(msgs.len());
     for msg_bytes in &msgs {
-        // attempt verification and match on error to find invalid message
-        match verify_message_value(&msg_bytes, hmac) {
-            Ok(_) => (),
-            Err(e) => {
-                let invalid_msg_str = std::str::from_utf8(msg_bytes).unwrap_or(
-                    "unable to convert invalid message bytes to string slice; not valid utf8",
-                );
-                let err_msg = format!("found invalid message: {}: {}", e, invalid_msg_str);
-                let response: (Option<String>, Option<Vec<String>>) = (Some(err_msg), None);
-                return JsValue::from_serde(&response)
-                    .expect("failed to serialize response with invalid message error");
-            }
+        match hmac_keys.verify(msg_bytes) {
+            Ok(idx) => matched_keys.push(idx),
+            Err(err_msg) => return error_response(err_msg),
         };
     }
 
     let keys = hash(msgs);
-    let response: (Option<String>, Option<Vec<String>>) = (None, Some(keys));
-    JsValue::from_serde(&response)
-        .expect("failed to serialize response with successfully verified keys")
+    let detailed = hmac_keys.is_list();
+    let report = BatchReport {
+        keys,
+        matched_keys: Some(matched_keys).filter(|_| detailed),
+        ..BatchReport::default()
+    };
+    report_response(report, detailed)
 }
 
 /// Verify signature and perform validation for a single message (includes HMAC key support).
 ///
 /// Takes an HMAC key as the first argument, message `value` as the second argument and an optional
-/// previous message `value` as the third argument. The HMAC key must be of type `ArrayBuffer`.
-/// Message signatures are verified without an HMAC key if the value of the argument
-/// is `null` or `undefined` (maps to a `None` value). The previous message argument is expected
-/// when the message to be validated is not the first in the feed (ie. sequence number != 1
-/// and previous != null).
+/// previous message `value` as the third argument. The HMAC key is an `HmacKey`; message
+/// signatures are verified without an HMAC key if it was created from `null` or `undefined`.
+/// The previous message argument is expected when the message to be validated is not the first
+/// in the feed (ie. sequence number != 1 and previous != null). In place of the full previous message `value`, a lightweight
+/// `{ key, sequence, author }` reference to it may be given.
 ///
 /// The return type is a tuple of `Option<String>`. The first element of the tuple holds the key
 /// (hash) of `msg_value` (if validation is successful) while the second element holds the error
@@ -126,32 +164,26 @@ pub fn verify_messages(hmac_key: JsValue, array: JsValue) -> JsValue {
 /// `err_msg` is of type `String` and includes the cause of the error and the offending message.
 #[wasm_bindgen(js_name = validateSingle)]
 pub fn verify_validate_message(
-    hmac_key: JsValue,
+    hmac_key: &HmacKey,
     message: String,
     previous: Option<String>,
 ) -> JsValue {
-    let hmac_key: Option<Vec<u8>> = match serde_wasm_bindgen::from_value(hmac_key) {
+    let hmac = match hmac_key.single() {
         Ok(hmac) => hmac,
-        Err(_) => {
-            let err_msg = "hmac key invalid: must be null, undefined, string or buffer".to_string();
-            let response: (Option<String>, Option<Vec<String>>) = (Some(err_msg), None);
-            return JsValue::from_serde(&response)
-                .expect("failed to serialize response for hmac key value error");
-        }
+        Err(err_msg) => return error_response(err_msg),
     };
 
-    let valid_hmac = match is_valid_hmac_key(hmac_key) {
-        Ok(key) => key,
-        Err(err_msg) => {
-            let response: (Option<String>, Option<Vec<String>>) = (Some(err_msg), None);
-            return JsValue::from_serde(&response)
-                .expect("failed to serialize response for invalid hmac key error");
-        }
-    };
-    let hmac = valid_hmac.as_deref();
+    let limits = Limits::defaults();
+    let checked = limits::check_message(0, &message, &limits).and_then(|_| match &previous {
+        Some(previous) => limits::check_message(1, previous, &limits),
+        None => Ok(()),
+    });
+    if let Err(err_msg) = checked {
+        return error_response(err_msg);
+    }
 
     let msg_bytes = message.into_bytes();
-    let previous_msg_bytes = previous.map(|msg| msg.into_bytes());
+    let previous = previous.map(|msg| Previous::parse(msg.into_bytes()));
 
     // attempt verification and match on error to find invalid message
     match verify_message_value(&msg_bytes, hmac) {
@@ -161,23 +193,28 @@ pub fn verify_validate_message(
                 "unable to convert invalid message bytes to string slice; not valid utf8",
             );
             let err_msg = format!("found invalid message: {}: {}", e, invalid_msg_str);
-            let response: (Option<String>, Option<Vec<String>>) = (Some(err_msg), None);
-            return JsValue::from_serde(&response)
-                .expect("failed to serialize response with invalid message error");
+            return error_response(err_msg);
         }
     };
 
     // attempt validation and match on error to find invalid message
-    match validate_message_value_hash_chain(&msg_bytes, previous_msg_bytes) {
+    let result = match previous {
+        Some(Previous::Tip(tip)) => state::validate_against_tip(&msg_bytes, &tip).map(|_| ()),
+        Some(Previous::Message(previous_msg_bytes)) => {
+            validate_message_value_hash_chain(&msg_bytes, Some(previous_msg_bytes))
+                .map_err(|e| e.to_string())
+        }
+        None => validate_message_value_hash_chain::<_, &[u8]>(&msg_bytes, None)
+            .map_err(|e| e.to_string()),
+    };
+    match result {
         Ok(_) => (),
         Err(e) => {
             let invalid_msg_str = std::str::from_utf8(&msg_bytes).unwrap_or(
                 "unable to convert invalid message bytes to string slice; not valid utf8",
             );
             let err_msg = format!("found invalid message: {}: {}", e, invalid_msg_str);
-            let response: (Option<String>, Option<Vec<String>>) = (Some(err_msg), None);
-            return JsValue::from_serde(&response)
-                .expect("failed to serialize response with invalid message error");
+            return error_response(err_msg);
         }
     }
 
@@ -193,251 +230,544 @@ pub fn verify_validate_message(
 /// author (includes HMAC key support).
 ///
 /// Takes an HMAC key as the first argument, an array of message values as the second argument
-/// and an optional previous message value as the third argument. The HMAC key must be of type
-/// `ArrayBuffer`. Message signatures are verified without an HMAC key if the value of the
-/// argument is `null` or `undefined` (maps to a `None` value). The previous message argument
-/// is expected when the array of messages does not start from the beginning of the feed
-/// (ie. sequence number != 1 and previous != null). If verification or validation fails, the
-/// cause of the error is returned along with the offending message.
+/// and an optional previous message value as the third argument. The HMAC key is an `HmacKey`;
+/// message signatures are verified without an HMAC key if it was created from `null` or
+/// `undefined`. The previous message argument is expected when the array of messages does not
+/// start from the beginning of the feed (ie. sequence number != 1 and previous != null) and may
+/// be a lightweight `{ key, sequence, author }` reference instead of the full message value. If verification or
+/// validation fails, the cause of the error is returned along with the offending message.
+///
+/// This is `validate` with the `"ordered"` ordering; the other options are only taken by
+/// `validate`.
 #[wasm_bindgen(js_name = validateBatch)]
 pub fn verify_validate_messages(
-    hmac_key: JsValue,
+    hmac_key: &HmacKey,
     array: JsValue,
     previous: Option<String>,
 ) -> JsValue {
-    let hmac_key: Option<Vec<u8>> = match serde_wasm_bindgen::from_value(hmac_key) {
-        Ok(hmac) => hmac,
-        Err(_) => {
-            let err_msg = "hmac key invalid: must be null, undefined, string or buffer".to_string();
-            let response: (Option<String>, Option<Vec<String>>) = (Some(err_msg), None);
-            return JsValue::from_serde(&response)
-                .expect("failed to serialize response for hmac key value error");
-        }
+    let options = ValidationOptions {
+        previous,
+        ..ValidationOptions::default()
     };
+    validate_with_options(hmac_key, array, options)
+}
 
-    let valid_hmac = match is_valid_hmac_key(hmac_key) {
-        Ok(key) => key,
-        Err(err_msg) => {
-            let response: (Option<String>, Option<Vec<String>>) = (Some(err_msg), None);
-            return JsValue::from_serde(&response)
-                .expect("failed to serialize response for invalid hmac key error");
-        }
+/// Verify signatures and perform validation for an array of out-of-order messages by a single
+/// author (includes HMAC key support).
+///
+/// Takes an HMAC key as the first argument and an array of messages as the second argument.
+/// The HMAC key is an `HmacKey`; message signatures are verified without an HMAC key if it was
+/// created from `null` or `undefined`. If verification or validation fails, the cause of the
+/// error is returned along with the offending message.
+///
+/// This is `validate` with the `"outOfOrder"` ordering; the other options are only taken by
+/// `validate`.
+#[wasm_bindgen(js_name = validateOOOBatch)]
+pub fn verify_validate_out_of_order_messages(hmac_key: &HmacKey, array: JsValue) -> JsValue {
+    let options = ValidationOptions {
+        ordering: Some(Ordering::OutOfOrder),
+        ..ValidationOptions::default()
     };
-    let hmac = valid_hmac.as_deref();
+    validate_with_options(hmac_key, array, options)
+}
 
-    let elements: Vec<String> = array
-        .into_serde()
-        .expect("failed to deserialize js message array into vector of strings");
-    let mut msgs = Vec::new();
-    for msg in elements {
-        let msg_bytes = msg.into_bytes();
-        msgs.push(msg_bytes)
+/// Verify signatures and perform validation for an array of out-of-order messages by multiple
+/// authors (includes HMAC key support).
+///
+/// Takes an HMAC key as the first argument and an array of messages as the second argument.
+/// The HMAC key is an `HmacKey`; message signatures are verified without an HMAC key if it was
+/// created from `null` or `undefined`. If verification or validation fails, the cause of the
+/// error is returned along with the offending message. An `HmacKey` of an array of HMAC keys or
+/// a `{ authors, default }` map of HMAC keys may be given in place of a single key, as for
+/// `verifySignatures`.
+///
+/// The array may mix classic messages with messages of the other feed formats (Bendy Butt,
+/// buttwoo and Gabby Grove), passed as JSON strings of their base64 encoding. A message value is
+/// validated as a classic message and must have a classic (`.ed25519`) author. An encoded message
+/// is decoded by the format picked by the first byte of its encoding, and its decoded author must
+/// be of that format, as given by the suffix of the author ID. A message value by an author of an
+/// unknown format, or an encoding of an unknown format, fails with an `unsupported feed format`
+/// error.
+///
+/// This is `validate` with the `"multiAuthor"` ordering; the other options are only taken by
+/// `validate`.
+#[wasm_bindgen(js_name = validateMultiAuthorBatch)]
+pub fn verify_validate_multi_author_messages(hmac_key: &HmacKey, array: JsValue) -> JsValue {
+    let options = ValidationOptions {
+        ordering: Some(Ordering::MultiAuthor),
+        ..ValidationOptions::default()
+    };
+    validate_with_options(hmac_key, array, options)
+}
+
+/// Verify signatures and perform validation for an array of messages, as configured by a single
+/// options object (includes HMAC key support).
+///
+/// Takes an `HmacKey` (of a single key, or an array or map of keys, as for `verifySignatures`)
+/// as the first argument, an array of messages as the second argument and an optional options
+/// object as the third argument. The options take the form
+/// `{ ordering, failFast, output, limits, timestampPolicy, forks, ...flags }`, where `ordering` is
+/// one of `"ordered"` (the default; see `validateBatch`), `"outOfOrder"` (see `validateOOOBatch`) or
+/// `"multiAuthor"` (see `validateMultiAuthorBatch`). Options which do not apply to the ordering
+/// are rejected.
+///
+/// The `limits` take the form `{ maxMessages, maxMessageBytes, maxDepth, maxTotalBytes }` and
+/// bound the number of messages, the byte length of each message, the JSON nesting depth of
+/// each message and the byte length of the whole batch. The count and lengths are checked on the
+/// JS array before the messages are copied into wasm memory, and all limits are checked again
+/// before any message is parsed or verified. Each limit left out takes its default (see
+/// `limits::Limits::defaults`), which every other function enforces as well.
+///
+/// The `timestampPolicy` takes the form `{ now, maxSkew, monotonic }`. A message with a
+/// timestamp more than `maxSkew` milliseconds ahead of `now` is rejected. If `monotonic` is
+/// `"warn"` or `"strict"`, a timestamp earlier than that of the preceding message of the same
+/// feed is reported in the `warnings` of the response (`{ keys, warnings }`) or rejected,
+/// respectively.
+///
+/// For an ordered batch, `previous` holds the previous message value (or a `{ key, sequence,
+/// author }` reference to it), as for `validateBatch`. If `autoSort` is `true`, the messages
+/// are sorted by sequence number before validation, so they may be passed in any order; the
+/// keys are returned in the order of the input. If `checkpoint` is `true`, the first message is
+/// trusted as a checkpoint: it is verified and validated without a predecessor and the rest of
+/// the chain is validated from it, and successful validation returns `{ keys, anchoredAt }`,
+/// where `anchoredAt` is the sequence number of the checkpoint. A checkpoint cannot be combined
+/// with a previous message.
+///
+/// For an out-of-order batch, if `checkLinks` is `true`, the hash-chain link is checked for
+/// every pair of messages with consecutive sequence numbers. If `reportGaps` is `true`,
+/// successful validation returns `{ keys, feeds }`, where `feeds` lists the minimum and maximum
+/// sequence number seen for each author along with the ranges of sequence numbers missing
+/// between them.
+///
+/// For an out-of-order or multi-author batch, exact duplicates (messages with the same key) are
+/// only verified and validated once. By default a key is still returned for every message. If
+/// `dedupe` is `true`, successful validation instead returns `{ keys, duplicates }`, where
+/// `keys` holds each unique key once and `duplicates` holds the positions of the input array
+/// which repeat an earlier message.
+///
+/// For a multi-author batch, if `chainPerAuthor` is `true`, the messages are grouped by author
+/// and the hash chain of each group is validated (in which case the messages of each author
+/// must form a contiguous run of the feed). The `authorFilter` takes the form
+/// `{ allow, block, skip }`, where `allow` and `block` are arrays of feed IDs. Messages by an
+/// author missing from `allow` (if given) or present in `block` are rejected before any
+/// signature is verified. If `skip` is `true`, they are instead left out of validation and the
+/// response includes `skipped`, the positions of the input array holding them; `keys` then
+/// holds the keys of the remaining messages.
+///
+/// If `ordering` is `"auto"`, the messages may be of any number of feeds in any order, and the
+/// strictest applicable validation is chosen for each feed: the messages of a feed forming a
+/// contiguous run are validated as a hash chain, while those of a sparse feed are validated
+/// out-of-order with the links between consecutive messages checked. Successful validation
+/// returns `{ keys, groups }`, where `groups` holds the `{ author, mode, minSequence,
+/// maxSequence, count }` of each feed and `mode` is `"fullChain"` or `"outOfOrder"`. Only
+/// `dedupe` may be given along with the general options.
+///
+/// Except for ordering `"auto"`, `rules` may be given to apply only some of the checks, e.g. to
+/// skip signature verification of messages which were verified before. It takes the form
+/// `{ signature, link, sequence, author, hash, length }`, where each rule is enabled unless set
+/// to `false`. A disabled rule leaves out its own check only, while every other check of
+/// `ssb-validate` still applies. `link` and `sequence` are only checked in an ordered batch,
+/// `author` in an ordered or out-of-order batch (see `rules::Rules`).
+///
+/// By default the report is returned in place of the array of keys only when a part of it was
+/// requested; `output` may be set to `"keys"` or `"report"` to choose either form.
+///
+/// `forks` chooses what to do with two different messages by the same author with the same
+/// sequence number (or a message conflicting with `previous`): `"error"` fails with a
+/// `fork detected` error, `"report"` returns each fork as `{ author, sequence, keys }` in
+/// `forks` along with the keys, and `"ignore"` leaves them unchecked. It defaults to `"error"`
+/// for the `"ordered"` and `"auto"` orderings, and to `"ignore"` for the others.
+///
+/// If `failFast` is `false`, every message is verified and validated rather than stopping at
+/// the first invalid one, and successful validation returns `{ keys, invalid }`, where `invalid`
+/// holds the `{ position, error }` of each invalid message. Only the checks made one message at
+/// a time are then applied (signature, message value and, for an ordered batch, the link to the
+/// preceding valid message), so only `previous`, `autoSort` and `checkpoint` may be combined
+/// with it, and `matchedKeys` is not reported.
+#[wasm_bindgen(js_name = validate)]
+pub fn validate(hmac_key: &HmacKey, array: JsValue, options: JsValue) -> JsValue {
+    match ValidationOptions::from_js(options) {
+        Ok(options) => validate_with_options(hmac_key, array, options),
+        Err(err_msg) => error_response(err_msg),
     }
+}
 
-    let previous_msg = previous.map(|msg| msg.into_bytes());
+/// Verify and validate an array of messages with the pipeline selected by the options (see
+/// `validate`).
+fn validate_with_options(
+    hmac_key: &HmacKey,
+    array: JsValue,
+    options: ValidationOptions,
+) -> JsValue {
+    let hmac_keys = hmac_key.keys();
 
-    // we're not running parallel verification here due to rayon issues for wasm:
-    // a dependency uses older versions of `rand` and `getrandom`, which fail to provide
-    // `thread_rng` when parallel verification is attempted in the browser.
-    for msg_bytes in &msgs {
-        // attempt verification and match on error to find invalid message
-        match verify_message_value(&msg_bytes, hmac) {
-            Ok(_) => (),
-            Err(e) => {
-                let invalid_msg_str = std::str::from_utf8(msg_bytes).unwrap_or(
-                    "unable to convert invalid message bytes to string slice; not valid utf8",
-                );
-                let err_msg = format!("found invalid message: {}: {}", e, invalid_msg_str);
-                let response: (Option<String>, Option<Vec<String>>) = (Some(err_msg), None);
-                return JsValue::from_serde(&response)
-                    .expect("failed to serialize response with invalid message error");
-            }
-        };
-    }
+    // the count and lengths of the messages are checked before they are copied out of JS
+    let elements = match limits::read_messages(&array, &options.limits()) {
+        Ok(elements) => elements,
+        Err(err_msg) => return error_response(err_msg),
+    };
 
-    // attempt batch validation and match on error to find invalid message
-    match par_validate_message_value_hash_chain_of_feed(&msgs, previous_msg.as_ref()) {
-        Ok(_) => (),
-        Err(e) => {
-            let invalid_msg = &msgs
-                .iter()
-                .find(|msg| validate_message_value_hash_chain(msg, previous_msg.as_ref()).is_err());
-            let invalid_msg_str = match invalid_msg {
-                Some(msg) => std::str::from_utf8(msg).unwrap_or(
-                    "unable to convert invalid message bytes to string slice; not valid utf8",
-                ),
-                None => "parallel validation failed but no single invalid message was found",
-            };
-            let err_msg = format!("found invalid message: {}: {}", e, invalid_msg_str);
-            let response: (Option<String>, Option<Vec<String>>) = (Some(err_msg), None);
-            return JsValue::from_serde(&response)
-                .expect("failed to serialize response with invalid message error");
+    let result = if !options.fail_fast() {
+        batch::validate_collect_all(hmac_keys, elements, &options)
+    } else {
+        match options.ordering() {
+            Ordering::Ordered => batch::validate_ordered(hmac_keys, elements, &options),
+            Ordering::OutOfOrder => batch::validate_out_of_order(hmac_keys, elements, &options),
+            Ordering::MultiAuthor => batch::validate_multi_author(hmac_keys, elements, &options),
+            Ordering::Auto => batch::validate_auto(hmac_keys, elements, &options),
         }
-    }
+    };
+    batch_response(result, options.output)
+}
 
-    let keys = hash(msgs);
-    let response: (Option<String>, Option<Vec<String>>) = (None, Some(keys));
-    JsValue::from_serde(&response)
-        .expect("failed to serialize response with successfully verified keys")
+/// Deserialize an array of binary messages passed in from JS (each a `Uint8Array`), within the
+/// default limits.
+fn parse_binary_messages(array: JsValue) -> Result<Vec<Vec<u8>>, String> {
+    limits::read_binary_messages(array, &Limits::defaults())
 }
 
-/// Verify signatures and perform validation for an array of out-of-order messages by a single
-/// author (includes HMAC key support).
-///
-/// Takes an HMAC key as the first argument and an array of messages as the second argument.
-/// The HMAC key must be of type `ArrayBuffer`. Message signatures are verified without an
-/// HMAC key if the value of the argument is `null` or `undefined` (maps to a `None` value).
-/// If verification or validation fails, the cause of the error is returned along with the
-/// offending message.
-#[wasm_bindgen(js_name = validateOOOBatch)]
-pub fn verify_validate_out_of_order_messages(hmac_key: JsValue, array: JsValue) -> JsValue {
-    let hmac_key: Option<Vec<u8>> = match serde_wasm_bindgen::from_value(hmac_key) {
-        Ok(hmac) => hmac,
-        Err(_) => {
-            let err_msg = "hmac key invalid: must be null, undefined, string or buffer".to_string();
-            let response: (Option<String>, Option<Vec<String>>) = (Some(err_msg), None);
-            return JsValue::from_serde(&response)
-                .expect("failed to serialize response for hmac key value error");
+/// Verify and validate a single message of a binary feed format, given its previous message (if
+/// any), within the default limits. Returns the key of the message.
+fn validate_binary_single<M: BinaryMsg>(
+    hmac_key: &HmacKey,
+    message: Vec<u8>,
+    previous: Option<Vec<u8>>,
+) -> JsValue {
+    let limits = Limits::defaults();
+    let response: (Option<String>, Option<String>) = match hmac_key.single().and_then(|hmac| {
+        limits::check_message_bytes(0, message.len(), &limits)?;
+        if let Some(previous) = &previous {
+            limits::check_message_bytes(1, previous.len(), &limits)?;
         }
+        feed::validate_batch::<M>(&[message], previous.as_deref(), hmac)
+    }) {
+        Ok(mut keys) => (None, keys.pop()),
+        Err(err_msg) => (Some(err_msg), None),
     };
+    JsValue::from_serde(&response).expect("failed to serialize response with validated key")
+}
 
-    let valid_hmac = match is_valid_hmac_key(hmac_key) {
-        Ok(key) => key,
-        Err(err_msg) => {
-            let response: (Option<String>, Option<Vec<String>>) = (Some(err_msg), None);
-            return JsValue::from_serde(&response)
-                .expect("failed to serialize response for invalid hmac key error");
-        }
+/// Verify and validate an ordered run of a single feed of a binary feed format, given the
+/// previous message (if any). Returns the key of each message.
+fn validate_binary_batch<M: BinaryMsg>(
+    hmac_key: &HmacKey,
+    array: JsValue,
+    previous: Option<Vec<u8>>,
+) -> JsValue {
+    let response: (Option<String>, Option<Vec<String>>) = match hmac_key.single().and_then(|hmac| {
+        let msgs = parse_binary_messages(array)?;
+        feed::validate_batch::<M>(&msgs, previous.as_deref(), hmac)
+    }) {
+        Ok(keys) => (None, Some(keys)),
+        Err(err_msg) => (Some(err_msg), None),
     };
-    let hmac = valid_hmac.as_deref();
+    JsValue::from_serde(&response)
+        .expect("failed to serialize response with successfully verified keys")
+}
 
-    let elements: Vec<String> = array
-        .into_serde()
-        .expect("failed to deserialize js message array into vector of strings");
-    let mut msgs = Vec::new();
-    for msg in elements {
-        let msg_bytes = msg.into_bytes();
-        msgs.push(msg_bytes)
-    }
+/// Verify and validate out-of-order messages of a binary feed format, each without reference to
+/// the others. Returns the key of each message.
+fn validate_binary_ooo_batch<M: BinaryMsg>(hmac_key: &HmacKey, array: JsValue) -> JsValue {
+    let response: (Option<String>, Option<Vec<String>>) = match hmac_key.single().and_then(|hmac| {
+        let msgs = parse_binary_messages(array)?;
+        feed::validate_ooo_batch::<M>(&msgs, hmac)
+    }) {
+        Ok(keys) => (None, Some(keys)),
+        Err(err_msg) => (Some(err_msg), None),
+    };
+    JsValue::from_serde(&response)
+        .expect("failed to serialize response with successfully verified keys")
+}
 
-    for msg_bytes in &msgs {
-        // attempt verification and match on error to find invalid message
-        match verify_message_value(&msg_bytes, hmac) {
-            Ok(_) => (),
-            Err(e) => {
-                let invalid_msg_str = std::str::from_utf8(msg_bytes).unwrap_or(
-                    "unable to convert invalid message bytes to string slice; not valid utf8",
-                );
-                let err_msg = format!("found invalid message: {}: {}", e, invalid_msg_str);
-                let response: (Option<String>, Option<Vec<String>>) = (Some(err_msg), None);
-                return JsValue::from_serde(&response)
-                    .expect("failed to serialize response with invalid message error");
-            }
+/// Verify signature and perform validation for a single Bendy Butt message (includes HMAC key
+/// support).
+///
+/// Takes an HMAC key as the first argument, the encoded message as the second argument and the
+/// optional encoded previous message as the third argument, as for `validateSingle`. The
+/// signature of the payload and the content signature (made by the subfeed the content refers
+/// to) are both verified. Successful validation returns the `%...=.bbmsg-v1` key of the message.
+#[wasm_bindgen(js_name = validateBendyButtSingle)]
+pub fn verify_validate_bendy_butt_message(
+    hmac_key: &HmacKey,
+    message: Vec<u8>,
+    previous: Option<Vec<u8>>,
+) -> JsValue {
+    validate_binary_single::<BendyButtMsg>(hmac_key, message, previous)
+}
+
+/// Verify signatures and perform validation for an array of ordered Bendy Butt messages by a
+/// single author (includes HMAC key support).
+///
+/// Takes an HMAC key as the first argument, an array of encoded messages as the second argument
+/// and the optional encoded previous message as the third argument, as for `validateBatch`.
+/// Successful validation returns the `%...=.bbmsg-v1` key of each message.
+#[wasm_bindgen(js_name = validateBendyButtBatch)]
+pub fn verify_validate_bendy_butt_messages(
+    hmac_key: &HmacKey,
+    array: JsValue,
+    previous: Option<Vec<u8>>,
+) -> JsValue {
+    validate_binary_batch::<BendyButtMsg>(hmac_key, array, previous)
+}
+
+/// Verify signatures and perform validation for an array of out-of-order Bendy Butt messages
+/// (includes HMAC key support).
+///
+/// Takes an HMAC key as the first argument and an array of encoded messages as the second
+/// argument, as for `validateOOOBatch`. Each message is verified and validated without
+/// reference to the others. Successful validation returns the `%...=.bbmsg-v1` key of each
+/// message.
+#[wasm_bindgen(js_name = validateBendyButtOOOBatch)]
+pub fn verify_validate_out_of_order_bendy_butt_messages(
+    hmac_key: &HmacKey,
+    array: JsValue,
+) -> JsValue {
+    validate_binary_ooo_batch::<BendyButtMsg>(hmac_key, array)
+}
+
+/// Verify signature and perform validation for a single buttwoo message (includes HMAC key
+/// support).
+///
+/// Takes an HMAC key as the first argument, the encoded message as the second argument and the
+/// optional encoded previous message as the third argument, as for `validateSingle`. The
+/// signature of the value is verified, along with the hash and length of the content (unless
+/// the content was dropped). Successful validation returns the `ssb:message/buttwoo-v1/...`
+/// key of the message.
+#[wasm_bindgen(js_name = validateButtwooSingle)]
+pub fn verify_validate_buttwoo_message(
+    hmac_key: &HmacKey,
+    message: Vec<u8>,
+    previous: Option<Vec<u8>>,
+) -> JsValue {
+    validate_binary_single::<ButtwooMsg>(hmac_key, message, previous)
+}
+
+/// Verify signatures and perform validation for an array of ordered buttwoo messages by a
+/// single author (includes HMAC key support).
+///
+/// Takes an HMAC key as the first argument, an array of encoded messages as the second argument
+/// and the optional encoded previous message as the third argument, as for `validateBatch`.
+/// Successful validation returns the `ssb:message/buttwoo-v1/...` key of each message.
+#[wasm_bindgen(js_name = validateButtwooBatch)]
+pub fn verify_validate_buttwoo_messages(
+    hmac_key: &HmacKey,
+    array: JsValue,
+    previous: Option<Vec<u8>>,
+) -> JsValue {
+    validate_binary_batch::<ButtwooMsg>(hmac_key, array, previous)
+}
+
+/// Verify signatures and perform validation for an array of out-of-order buttwoo messages
+/// (includes HMAC key support).
+///
+/// Takes an HMAC key as the first argument and an array of encoded messages as the second
+/// argument, as for `validateOOOBatch`. Each message is verified and validated without
+/// reference to the others. Successful validation returns the `ssb:message/buttwoo-v1/...` key
+/// of each message.
+#[wasm_bindgen(js_name = validateButtwooOOOBatch)]
+pub fn verify_validate_out_of_order_buttwoo_messages(
+    hmac_key: &HmacKey,
+    array: JsValue,
+) -> JsValue {
+    validate_binary_ooo_batch::<ButtwooMsg>(hmac_key, array)
+}
+
+/// Verify signature and perform validation for a single Gabby Grove event (includes HMAC key
+/// support).
+///
+/// Takes an HMAC key as the first argument, the encoded message as the second argument and the
+/// optional encoded previous message as the third argument, as for `validateSingle`. The
+/// signature of the event is verified, along with the hash and size of the content (unless the
+/// content was not transferred). Successful validation returns the `%...=.ggmsg-v1` key of the
+/// message.
+#[wasm_bindgen(js_name = validateGabbyGroveSingle)]
+pub fn verify_validate_gabby_grove_message(
+    hmac_key: &HmacKey,
+    message: Vec<u8>,
+    previous: Option<Vec<u8>>,
+) -> JsValue {
+    validate_binary_single::<GabbyGroveMsg>(hmac_key, message, previous)
+}
+
+/// Verify signatures and perform validation for an array of ordered Gabby Grove events by a
+/// single author (includes HMAC key support).
+///
+/// Takes an HMAC key as the first argument, an array of encoded messages as the second argument
+/// and the optional encoded previous message as the third argument, as for `validateBatch`.
+/// Successful validation returns the `%...=.ggmsg-v1` key of each message.
+#[wasm_bindgen(js_name = validateGabbyGroveBatch)]
+pub fn verify_validate_gabby_grove_messages(
+    hmac_key: &HmacKey,
+    array: JsValue,
+    previous: Option<Vec<u8>>,
+) -> JsValue {
+    validate_binary_batch::<GabbyGroveMsg>(hmac_key, array, previous)
+}
+
+/// Verify signatures and perform validation for an array of out-of-order Gabby Grove events
+/// (includes HMAC key support).
+///
+/// Takes an HMAC key as the first argument and an array of encoded messages as the second
+/// argument, as for `validateOOOBatch`. Each message is verified and validated without
+/// reference to the others. Successful validation returns the `%...=.ggmsg-v1` key
+/// of each message.
+#[wasm_bindgen(js_name = validateGabbyGroveOOOBatch)]
+pub fn verify_validate_out_of_order_gabby_grove_messages(
+    hmac_key: &HmacKey,
+    array: JsValue,
+) -> JsValue {
+    validate_binary_ooo_batch::<GabbyGroveMsg>(hmac_key, array)
+}
+
+/// Verify a single Bamboo log entry, along with the entries it links to when given.
+///
+/// Takes the encoded entry as the first argument, its optional payload as the second argument
+/// and the optional encoded lipmaa and backlink entries as the third and fourth arguments. The
+/// signature is verified, as are the size and hash of the payload and the links to the given
+/// entries. Bamboo is not a Scuttlebutt format, so no HMAC key is taken. Successful validation
+/// returns the hex encoded YAMF hash of the entry.
+#[wasm_bindgen(js_name = validateBambooSingle)]
+pub fn verify_validate_bamboo_entry(
+    entry: Vec<u8>,
+    payload: Option<Vec<u8>>,
+    lipmaa_entry: Option<Vec<u8>>,
+    backlink_entry: Option<Vec<u8>>,
+) -> JsValue {
+    let response: (Option<String>, Option<String>) = match bamboo::validate_single(
+        &entry,
+        payload.as_deref(),
+        lipmaa_entry.as_deref(),
+        backlink_entry.as_deref(),
+    ) {
+        Ok(key) => (None, Some(key)),
+        Err(err_msg) => (Some(err_msg), None),
+    };
+    JsValue::from_serde(&response).expect("failed to serialize response with validated key")
+}
+
+/// Verify a pool of Bamboo log entries of a single log, in any order and with any gaps.
+///
+/// Takes an array of encoded entries as the first argument and an optional array of payloads
+/// (each a buffer, or `null` when not available) at the same positions as the second argument,
+/// which must hold one payload for each entry when given.
+/// Every entry is verified on its own, and every lipmaa link and backlink to another entry of
+/// the pool is checked. Successful validation returns `{ keys, anchored }`: the hex encoded YAMF
+/// hash of each entry and whether its certificate pool (the entries on its lipmaa path back to
+/// the first entry) is present, so that it is verified back to the start of the log.
+#[wasm_bindgen(js_name = validateBambooBatch)]
+pub fn verify_validate_bamboo_entries(entries: JsValue, payloads: JsValue) -> JsValue {
+    let payloads = if payloads.is_null() || payloads.is_undefined() {
+        Ok(None)
+    } else {
+        serde_wasm_bindgen::from_value::<Vec<Option<Vec<u8>>>>(payloads)
+            .map(Some)
+            .map_err(|_| "payloads invalid: must be an array of buffers or null".to_string())
+    };
+    let response: (Option<String>, Option<bamboo::BambooReport>) =
+        match parse_binary_messages(entries)
+            .and_then(|entries| bamboo::validate_batch(&entries, payloads?.as_deref()))
+        {
+            Ok(report) => (None, Some(report)),
+            Err(err_msg) => (Some(err_msg), None),
         };
-    }
+    JsValue::from_serde(&response).expect("failed to serialize response with validated entries")
+}
 
-    // attempt batch validation and match on error to find invalid message
-    // TODO: do we really not care about the previous msg here?!
-    match par_validate_ooo_message_value_hash_chain_of_feed::<_, &[u8]>(&msgs, None) {
-        Ok(_) => (),
-        Err(e) => {
-            let invalid_msg = &msgs
-                .iter()
-                .find(|msg| validate_ooo_message_value_hash_chain::<_, &[u8]>(msg, None).is_err());
-            let invalid_msg_str = match invalid_msg {
-                Some(msg) => std::str::from_utf8(msg).unwrap_or(
-                    "unable to convert invalid message bytes to string slice; not valid utf8",
-                ),
-                None => "parallel validation failed but no single invalid message was found",
-            };
-            let err_msg = format!("found invalid message: {}: {}", e, invalid_msg_str);
-            let response: (Option<String>, Option<Vec<String>>) = (Some(err_msg), None);
-            return JsValue::from_serde(&response)
-                .expect("failed to serialize response with invalid message error");
-        }
-    }
+/// Resolve and check the consistency of a tree of metafeeds (includes HMAC key support).
+///
+/// Takes an HMAC key as the first argument, an array of encoded Bendy Butt messages of any
+/// number of metafeeds (in any order) as the second argument and an array of main feed messages
+/// as the third argument. The metafeed messages are verified, including the content signature
+/// of each `metafeed/add/existing`, `metafeed/add/derived` and `metafeed/tombstone` message,
+/// while the main feed messages are expected to have been validated already and are only
+/// checked for `metafeed/announce` messages.
+///
+/// Successful validation returns `{ roots, inconsistencies }`, where `roots` holds the tree of
+/// each metafeed which is not a subfeed of another given metafeed, as
+/// `{ id, announcedBy, subfeeds }` with each subfeed given as
+/// `{ id, feedpurpose, kind, key, tombstoned, metafeed }`. Each `{ feed, reason }` of
+/// `inconsistencies` reports a main feed added to a metafeed which it does not announce (or the
+/// reverse), a subfeed added to more than one metafeed or a malformed announcement.
+#[wasm_bindgen(js_name = validateMetafeedTree)]
+pub fn validate_metafeed_tree(
+    hmac_key: &HmacKey,
+    metafeed_msgs: JsValue,
+    main_feed_msgs: JsValue,
+) -> JsValue {
+    let hmac = match hmac_key.single() {
+        Ok(hmac) => hmac,
+        Err(err_msg) => return error_response(err_msg),
+    };
 
-    let keys = hash(msgs);
-    let response: (Option<String>, Option<Vec<String>>) = (None, Some(keys));
-    JsValue::from_serde(&response)
-        .expect("failed to serialize response with successfully verified keys")
+    let main_feed_msgs = match limits::read_messages(&main_feed_msgs, &Limits::defaults()) {
+        Ok(main_feed_msgs) => main_feed_msgs,
+        Err(err_msg) => return error_response(err_msg),
+    };
+
+    let response: (Option<String>, Option<metafeed::TreeReport>) =
+        match parse_binary_messages(metafeed_msgs)
+            .and_then(|msgs| metafeed::validate_tree(&msgs, &main_feed_msgs, hmac))
+        {
+            Ok(report) => (None, Some(report)),
+            Err(err_msg) => (Some(err_msg), None),
+        };
+    JsValue::from_serde(&response).expect("failed to serialize response with metafeed tree")
 }
 
-/// Verify signatures and perform validation for an array of out-of-order messages by multiple
-/// authors (includes HMAC key support).
+/// Detect forks in an array of messages (includes HMAC key support).
 ///
-/// Takes an HMAC key as the first argument and an array of messages as the second argument.
-/// The HMAC key must be of type `ArrayBuffer`. Message signatures are verified without an
-/// HMAC key if the value of the argument is `null` or `undefined` (maps to a `None` value).
-/// If verification or validation fails, the cause of the error is returned along with the
-/// offending message.
-#[wasm_bindgen(js_name = validateMultiAuthorBatch)]
-pub fn verify_validate_multi_author_messages(hmac_key: JsValue, array: JsValue) -> JsValue {
-    let hmac_key: Option<Vec<u8>> = match serde_wasm_bindgen::from_value(hmac_key) {
+/// Takes an HMAC key as the first argument, an array of messages as the second argument and an
+/// optional array of known feed tips (`{ author, sequence, key }`) as the third argument. The
+/// HMAC key is an `HmacKey`; message signatures are verified without an HMAC key if it was
+/// created from `null` or `undefined`. Signatures
+/// are verified first so that a forged message cannot be reported as a fork.
+///
+/// A fork is reported for every message which has the same author and sequence number as, but
+/// a different key to, another message in the array or a known tip. Each report takes the form
+/// `{ author, sequence, keys: [first, second] }`. If verification fails, the cause of the error
+/// is returned along with the offending message.
+#[wasm_bindgen(js_name = detectForks)]
+pub fn detect_forks(hmac_key: &HmacKey, array: JsValue, known_tips: JsValue) -> JsValue {
+    let hmac = match hmac_key.single() {
         Ok(hmac) => hmac,
-        Err(_) => {
-            let err_msg = "hmac key invalid: must be null, undefined, string or buffer".to_string();
-            let response: (Option<String>, Option<Vec<String>>) = (Some(err_msg), None);
-            return JsValue::from_serde(&response)
-                .expect("failed to serialize response for hmac key value error");
-        }
+        Err(err_msg) => return error_response(err_msg),
     };
 
-    let valid_hmac = match is_valid_hmac_key(hmac_key) {
-        Ok(key) => key,
-        Err(err_msg) => {
-            let response: (Option<String>, Option<Vec<String>>) = (Some(err_msg), None);
-            return JsValue::from_serde(&response)
-                .expect("failed to serialize response for invalid hmac key error");
+    let elements = match limits::read_messages(&array, &Limits::defaults()) {
+        Ok(elements) => elements,
+        Err(err_msg) => return error_response(err_msg),
+    };
+    let msgs: Vec<Vec<u8>> = elements.into_iter().map(String::into_bytes).collect();
+
+    let tips: Vec<FeedTip> = if known_tips.is_null() || known_tips.is_undefined() {
+        Vec::new()
+    } else {
+        match known_tips.into_serde() {
+            Ok(tips) => tips,
+            Err(_) => {
+                let err_msg =
+                    "known tips invalid: must be an array of { author, sequence, key }".to_string();
+                return error_response(err_msg);
+            }
         }
     };
-    let hmac = valid_hmac.as_deref();
-
-    let elements: Vec<String> = array
-        .into_serde()
-        .expect("failed to deserialize js message array into vector of strings");
-    let mut msgs = Vec::new();
-    for msg in elements {
-        let msg_bytes = msg.into_bytes();
-        msgs.push(msg_bytes)
-    }
 
     for msg_bytes in &msgs {
         // attempt verification and match on error to find invalid message
-        match verify_message_value(&msg_bytes, hmac) {
-            Ok(_) => (),
-            Err(e) => {
-                let invalid_msg_str = std::str::from_utf8(msg_bytes).unwrap_or(
-                    "unable to convert invalid message bytes to string slice; not valid utf8",
-                );
-                let err_msg = format!("found invalid message: {}: {}", e, invalid_msg_str);
-                let response: (Option<String>, Option<Vec<String>>) = (Some(err_msg), None);
-                return JsValue::from_serde(&response)
-                    .expect("failed to serialize response with invalid message error");
-            }
-        };
-    }
-
-    // attempt batch validation and match on error to find invalid message
-    match par_validate_message_value(&msgs) {
-        Ok(_) => (),
-        Err(e) => {
-            let invalid_msg = &msgs.iter().find(|msg| validate_message_value(msg).is_err());
-            let invalid_msg_str = match invalid_msg {
-                Some(msg) => std::str::from_utf8(msg).unwrap_or(
-                    "unable to convert invalid message bytes to string slice; not valid utf8",
-                ),
-                None => "parallel validation failed but no single invalid message was found",
-            };
-            let err_msg = format!("found invalid message: {}: {}", e, invalid_msg_str);
-            let response: (Option<String>, Option<Vec<String>>) = (Some(err_msg), None);
-            return JsValue::from_serde(&response)
-                .expect("failed to serialize response with invalid message error");
+        if let Err(e) = verify_message_value(msg_bytes, hmac) {
+            let err_msg = format!(
+                "found invalid message: {}: {}",
+                e,
+                message::as_str(msg_bytes)
+            );
+            return error_response(err_msg);
         }
     }
 
-    let keys = hash(msgs);
-    let response: (Option<String>, Option<Vec<String>>) = (None, Some(keys));
-    JsValue::from_serde(&response)
-        .expect("failed to serialize response with successfully verified keys")
+    let response: (Option<String>, Option<Vec<fork::Fork>>) = match fork::detect_forks(&msgs, &tips)
+    {
+        Ok(forks) => (None, Some(forks)),
+        Err(err_msg) => (Some(err_msg), None),
+    };
+    JsValue::from_serde(&response).expect("failed to serialize response with fork reports")
 }
@@ -0,0 +1,239 @@
+// SPDX-FileCopyrightText: 2021 Andrew 'glyph' Reid
+//
+// SPDX-License-Identifier: LGPL-3.0-only
+
+use js_sys::{Array, JsString, Uint8Array};
+use serde::Deserialize;
+use wasm_bindgen::{JsCast, JsValue};
+
+/// Bounds on the size of the input, enforced before any message is parsed or verified.
+///
+/// Every exported function which takes messages enforces the default limits (see
+/// `Limits::defaults`). `validate` also takes limits of its own (the `limits` option), each of
+/// which replaces its default.
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Limits {
+    /// The maximum number of messages in a batch.
+    pub max_messages: Option<usize>,
+    /// The maximum byte length of a single (serialized) message.
+    pub max_message_bytes: Option<usize>,
+    /// The maximum nesting depth of objects and arrays within a message.
+    pub max_depth: Option<usize>,
+    /// The maximum byte length of all messages in a batch combined.
+    pub max_total_bytes: Option<usize>,
+}
+
+/// The limits enforced when none are given, far above the size of any valid message.
+const DEFAULTS: Limits = Limits {
+    max_messages: Some(100_000),
+    // a classic message is at most 8192 UTF-16 code units, a Bendy Butt message 8192 bytes and
+    // the content of a buttwoo message 16384 bytes
+    max_message_bytes: Some(64 * 1024),
+    // a message nested any deeper fails to parse anyway (the recursion limit of `serde_json`)
+    max_depth: Some(128),
+    max_total_bytes: Some(64 * 1024 * 1024),
+};
+
+impl Limits {
+    pub fn defaults() -> Limits {
+        DEFAULTS
+    }
+
+    /// Fill in each limit which is left out with its default.
+    pub fn or_defaults(self) -> Limits {
+        Limits {
+            max_messages: self.max_messages.or(DEFAULTS.max_messages),
+            max_message_bytes: self.max_message_bytes.or(DEFAULTS.max_message_bytes),
+            max_depth: self.max_depth.or(DEFAULTS.max_depth),
+            max_total_bytes: self.max_total_bytes.or(DEFAULTS.max_total_bytes),
+        }
+    }
+}
+
+/// Return the maximum nesting depth of objects and arrays in a serialized JSON message.
+///
+/// This is a single pass over the bytes (skipping the contents of strings) which does not
+/// otherwise check that the message is well-formed.
+fn json_depth(msg: &[u8]) -> usize {
+    let mut depth: usize = 0;
+    let mut max_depth = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+    for byte in msg {
+        if in_string {
+            match byte {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => (),
+            }
+            continue;
+        }
+        match byte {
+            b'"' => in_string = true,
+            b'{' | b'[' => {
+                depth += 1;
+                max_depth = max_depth.max(depth);
+            }
+            b'}' | b']' => depth = depth.saturating_sub(1),
+            _ => (),
+        }
+    }
+    max_depth
+}
+
+/// Check the number of messages and the length of each message against the limits while the
+/// batch is still a JS array, before the messages are copied into wasm memory.
+///
+/// The length of a JS string is counted in UTF-16 code units, each of which takes at least one
+/// byte when encoded as UTF-8, so a batch which exceeds the byte limits here exceeds them once
+/// copied too. The exact byte lengths and the nesting depth are checked by `check_limits`.
+pub fn check_js_limits(array: &JsValue, limits: &Limits) -> Result<(), String> {
+    let array = match array.dyn_ref::<Array>() {
+        Some(array) => array,
+        None => return Err("messages invalid: must be an array of strings".to_string()),
+    };
+
+    let count = array.length() as usize;
+    if let Some(max_messages) = limits.max_messages {
+        if count > max_messages {
+            return Err(format!(
+                "limit exceeded: batch has {} messages, more than the maximum of {}",
+                count, max_messages
+            ));
+        }
+    }
+
+    let mut total_length = 0;
+    for (position, msg) in array.iter().enumerate() {
+        // elements which are not strings are rejected when the batch is deserialized
+        let length = match msg.dyn_ref::<JsString>() {
+            Some(msg) => msg.length() as usize,
+            None => continue,
+        };
+        if let Some(max_message_bytes) = limits.max_message_bytes {
+            if length > max_message_bytes {
+                return Err(format!(
+                    "limit exceeded: message at position {} has at least {} bytes, more than the maximum of {}",
+                    position, length, max_message_bytes
+                ));
+            }
+        }
+        total_length += length;
+    }
+    if let Some(max_total_bytes) = limits.max_total_bytes {
+        if total_length > max_total_bytes {
+            return Err(format!(
+                "limit exceeded: batch has at least {} bytes, more than the maximum of {}",
+                total_length, max_total_bytes
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Check the number and byte length of a batch of binary messages (each a `Uint8Array`) against
+/// the limits while it is still a JS array, then copy it out of JS.
+pub fn read_binary_messages(array: JsValue, limits: &Limits) -> Result<Vec<Vec<u8>>, String> {
+    let invalid = || "messages invalid: must be an array of buffers".to_string();
+    let lengths: Vec<usize> = array
+        .dyn_ref::<Array>()
+        .ok_or_else(invalid)?
+        .iter()
+        .map(|msg| msg.dyn_ref::<Uint8Array>().map(|msg| msg.length() as usize))
+        .collect::<Option<Vec<usize>>>()
+        .ok_or_else(invalid)?;
+
+    if let Some(max_messages) = limits.max_messages {
+        if lengths.len() > max_messages {
+            return Err(format!(
+                "limit exceeded: batch has {} messages, more than the maximum of {}",
+                lengths.len(),
+                max_messages
+            ));
+        }
+    }
+    for (position, length) in lengths.iter().enumerate() {
+        check_message_bytes(position, *length, limits)?;
+    }
+    if let Some(max_total_bytes) = limits.max_total_bytes {
+        let total_bytes: usize = lengths.iter().sum();
+        if total_bytes > max_total_bytes {
+            return Err(format!(
+                "limit exceeded: batch has {} bytes, more than the maximum of {}",
+                total_bytes, max_total_bytes
+            ));
+        }
+    }
+
+    serde_wasm_bindgen::from_value(array).map_err(|_| invalid())
+}
+
+/// Check the byte length of a single message against the limits.
+pub fn check_message_bytes(position: usize, length: usize, limits: &Limits) -> Result<(), String> {
+    match limits.max_message_bytes {
+        Some(max_message_bytes) if length > max_message_bytes => Err(format!(
+            "limit exceeded: message at position {} has {} bytes, more than the maximum of {}",
+            position, length, max_message_bytes
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Check the limits of a batch of serialized messages while it is still a JS array (see
+/// `check_js_limits`), then copy it out of JS and check it again in full (see `check_limits`).
+pub fn read_messages(array: &JsValue, limits: &Limits) -> Result<Vec<String>, String> {
+    check_js_limits(array, limits)?;
+    let msgs: Vec<String> = array
+        .into_serde()
+        .map_err(|_| "messages invalid: must be an array of strings".to_string())?;
+    check_limits(&msgs, limits)?;
+    Ok(msgs)
+}
+
+/// Check a batch of serialized messages against the limits. The error for a message which is
+/// too large includes its position in the batch rather than the message itself.
+pub fn check_limits(msgs: &[String], limits: &Limits) -> Result<(), String> {
+    if let Some(max_messages) = limits.max_messages {
+        if msgs.len() > max_messages {
+            return Err(format!(
+                "limit exceeded: batch has {} messages, more than the maximum of {}",
+                msgs.len(),
+                max_messages
+            ));
+        }
+    }
+
+    if let Some(max_total_bytes) = limits.max_total_bytes {
+        let total_bytes: usize = msgs.iter().map(|msg| msg.len()).sum();
+        if total_bytes > max_total_bytes {
+            return Err(format!(
+                "limit exceeded: batch has {} bytes, more than the maximum of {}",
+                total_bytes, max_total_bytes
+            ));
+        }
+    }
+
+    for (position, msg) in msgs.iter().enumerate() {
+        check_message(position, msg, limits)?;
+    }
+
+    Ok(())
+}
+
+/// Check the byte length and nesting depth of a single serialized message against the limits.
+pub fn check_message(position: usize, msg: &str, limits: &Limits) -> Result<(), String> {
+    check_message_bytes(position, msg.len(), limits)?;
+    if let Some(max_depth) = limits.max_depth {
+        let depth = json_depth(msg.as_bytes());
+        if depth > max_depth {
+            return Err(format!(
+                "limit exceeded: message at position {} is nested {} levels deep, more than the maximum of {}",
+                position, depth, max_depth
+            ));
+        }
+    }
+    Ok(())
+}
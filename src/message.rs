@@ -0,0 +1,34 @@
+// SPDX-FileCopyrightText: 2021 Andrew 'glyph' Reid
+//
+// SPDX-License-Identifier: LGPL-3.0-only
+
+use serde::Deserialize;
+use ssb_validate::utils;
+
+/// The subset of message value fields needed to reason about feed position.
+///
+/// Deserializing into this struct does not validate the message; full validation is still
+/// performed by `ssb_validate`.
+#[derive(Debug, Deserialize)]
+pub struct MessageMeta {
+    pub author: String,
+    pub sequence: u64,
+    pub previous: Option<String>,
+}
+
+/// Parse the author, sequence number and previous key from message value bytes.
+pub fn parse_meta(msg: &[u8]) -> Result<MessageMeta, String> {
+    serde_json::from_slice(msg).map_err(|e| format!("failed to parse message value fields: {}", e))
+}
+
+/// Compute the key (hash) of a message value.
+pub fn key(msg: &[u8]) -> String {
+    let multihash = utils::multihash_from_bytes(msg);
+    multihash.to_legacy_string()
+}
+
+/// Convert message bytes to a string slice for inclusion in error messages.
+pub fn as_str(msg: &[u8]) -> &str {
+    std::str::from_utf8(msg)
+        .unwrap_or("unable to convert invalid message bytes to string slice; not valid utf8")
+}
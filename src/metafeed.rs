@@ -0,0 +1,408 @@
+// SPDX-FileCopyrightText: 2021 Andrew 'glyph' Reid
+//
+// SPDX-License-Identifier: LGPL-3.0-only
+
+//! Consistency of metafeed trees.
+//!
+//! A metafeed (a Bendy Butt feed) adds subfeeds with `metafeed/add/existing` and
+//! `metafeed/add/derived` messages and removes them with `metafeed/tombstone` messages, the
+//! content of each signed by the subfeed (`contentSignature`). A main feed which was added as
+//! an existing subfeed announces its metafeed in turn with a `metafeed/announce` message. The
+//! tree is resolved from these messages and every announcement which is not matched from the
+//! other side is reported.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bencode::Value,
+    bendybutt::{self, BendyButtMsg},
+    bfe,
+    feed::{self, BinaryMsg},
+    format::FeedFormat,
+    message,
+};
+
+const ADD_EXISTING: &str = "metafeed/add/existing";
+const ADD_DERIVED: &str = "metafeed/add/derived";
+const TOMBSTONE: &str = "metafeed/tombstone";
+const ANNOUNCE: &str = "metafeed/announce";
+
+/// How a subfeed was added to its metafeed.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SubfeedKind {
+    /// An existing feed, such as the main feed.
+    Existing,
+    /// A feed derived from the seed of the metafeed.
+    Derived,
+}
+
+/// A subfeed of a metafeed.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Subfeed {
+    pub id: String,
+    pub feedpurpose: String,
+    pub kind: SubfeedKind,
+    /// Key of the message which added the subfeed.
+    pub key: String,
+    /// Whether the subfeed was later removed from the metafeed.
+    pub tombstoned: bool,
+    /// The tree of the subfeed, when it is itself a metafeed whose messages were given.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metafeed: Option<MetafeedTree>,
+}
+
+/// A metafeed and its subfeeds.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MetafeedTree {
+    pub id: String,
+    /// The main feeds which announce the metafeed, where each announcement is matched by the
+    /// metafeed adding the main feed.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub announced_by: Vec<String>,
+    pub subfeeds: Vec<Subfeed>,
+}
+
+/// An announcement which is not matched from the other side, or is otherwise malformed.
+#[derive(Debug, Serialize)]
+pub struct Inconsistency {
+    /// The feed which made the announcement.
+    pub feed: String,
+    pub reason: String,
+}
+
+/// The resolved trees of the given metafeeds, along with every inconsistency found.
+#[derive(Debug, Default, Serialize)]
+pub struct TreeReport {
+    /// Each metafeed which is not a subfeed of another given metafeed.
+    pub roots: Vec<MetafeedTree>,
+    pub inconsistencies: Vec<Inconsistency>,
+}
+
+/// The fields of a main feed message checked for an announcement.
+#[derive(Deserialize)]
+struct AnnounceFields {
+    author: String,
+    content: serde_json::Value,
+}
+
+fn text<'a>(content: &'a Value, key: &str) -> Option<&'a str> {
+    content
+        .get(key)
+        .and_then(Value::as_bytes)
+        .and_then(|bytes| std::str::from_utf8(bytes).ok())
+}
+
+/// The subfeeds of each metafeed, as added and removed by its messages.
+struct Resolver {
+    subfeeds: BTreeMap<String, Vec<Subfeed>>,
+    inconsistencies: Vec<Inconsistency>,
+}
+
+impl Resolver {
+    fn inconsistent(&mut self, feed: &str, reason: String) {
+        self.inconsistencies.push(Inconsistency {
+            feed: feed.to_string(),
+            reason,
+        });
+    }
+
+    /// Apply the content of a message of the given metafeed, in sequence order.
+    fn apply(&mut self, metafeed: &str, key: String, content: &Value) {
+        let msg_type = text(content, "type").unwrap_or_default();
+        let kind = match msg_type {
+            ADD_EXISTING => Some(SubfeedKind::Existing),
+            ADD_DERIVED => Some(SubfeedKind::Derived),
+            TOMBSTONE => None,
+            _ => return,
+        };
+
+        // the subfeed was checked when the content signature was verified
+        let subfeed = match content
+            .get("subfeed")
+            .and_then(Value::as_bytes)
+            .map(bfe::feed_id)
+        {
+            Some(Ok(subfeed)) => subfeed,
+            Some(Err(e)) => return self.inconsistent(metafeed, format!("{}: {}", key, e)),
+            None => return self.inconsistent(metafeed, format!("{}: missing subfeed", key)),
+        };
+        let named = content
+            .get("metafeed")
+            .and_then(Value::as_bytes)
+            .map(bfe::feed_id);
+        if !matches!(&named, Some(Ok(named)) if named == metafeed) {
+            self.inconsistent(
+                metafeed,
+                format!(
+                    "{} of {} does not name its author as the metafeed",
+                    msg_type, subfeed
+                ),
+            );
+        }
+
+        let kind = match kind {
+            Some(kind) => kind,
+            None => {
+                let added = self.subfeeds.get_mut(metafeed).and_then(|subfeeds| {
+                    subfeeds
+                        .iter_mut()
+                        .find(|added| added.id == subfeed && !added.tombstoned)
+                });
+                match added {
+                    Some(added) => added.tombstoned = true,
+                    None => self.inconsistent(
+                        metafeed,
+                        format!("tombstone of {} which was not added", subfeed),
+                    ),
+                }
+                return;
+            }
+        };
+        if kind == SubfeedKind::Derived
+            && content
+                .get("nonce")
+                .and_then(Value::as_bytes)
+                .map(<[u8]>::len)
+                != Some(32)
+        {
+            self.inconsistent(
+                metafeed,
+                format!("{} of {} must have a nonce of 32 bytes", msg_type, subfeed),
+            );
+        }
+        let already_added = self.subfeeds.iter().find_map(|(other, subfeeds)| {
+            subfeeds
+                .iter()
+                .find(|added| added.id == subfeed && !added.tombstoned)
+                .map(|_| other.clone())
+        });
+        if let Some(other) = already_added {
+            self.inconsistent(
+                metafeed,
+                format!("{} was already added to metafeed {}", subfeed, other),
+            );
+        }
+
+        self.subfeeds
+            .entry(metafeed.to_string())
+            .or_default()
+            .push(Subfeed {
+                id: subfeed,
+                feedpurpose: text(content, "feedpurpose").unwrap_or_default().to_string(),
+                kind,
+                key,
+                tombstoned: false,
+                metafeed: None,
+            });
+    }
+
+    /// Build the tree of a metafeed, nesting the trees of subfeeds which are themselves given
+    /// metafeeds. The metafeeds of `path` lead to this one from the root.
+    fn build(
+        &mut self,
+        metafeed: &str,
+        announced_by: &HashMap<String, BTreeSet<String>>,
+        path: &mut Vec<String>,
+    ) -> MetafeedTree {
+        path.push(metafeed.to_string());
+        let mut subfeeds = self.subfeeds.remove(metafeed).unwrap_or_default();
+        for subfeed in &mut subfeeds {
+            if path.contains(&subfeed.id) {
+                let reason = format!("adds {} which is one of its own ancestors", subfeed.id);
+                self.inconsistent(metafeed, reason);
+            } else if self.subfeeds.contains_key(&subfeed.id) {
+                subfeed.metafeed = Some(self.build(&subfeed.id, announced_by, path));
+            }
+        }
+        path.pop();
+        MetafeedTree {
+            id: metafeed.to_string(),
+            announced_by: announced_by
+                .get(metafeed)
+                .map(|main_feeds| main_feeds.iter().cloned().collect())
+                .unwrap_or_default(),
+            subfeeds,
+        }
+    }
+}
+
+/// Verify the given metafeed messages (including the content signature of each) and resolve
+/// the tree of metafeeds they describe, checking it against the announcements of the given
+/// main feed messages.
+///
+/// Every main feed added to a metafeed as an existing feed should announce that metafeed, and
+/// every announced metafeed should add the main feed. Announcements which are not matched from
+/// the other side are reported as inconsistencies, as are malformed or repeated additions and
+/// tombstones of subfeeds which were never added.
+///
+/// The messages of each metafeed are linked in sequence order. Messages missing from the
+/// sequence are reported, and the messages after them are still applied. A fork, or a message
+/// which does not link to its predecessor, is reported and stops the messages of that metafeed
+/// from being applied any further.
+pub fn validate_tree(
+    metafeed_msgs: &[Vec<u8>],
+    main_feed_msgs: &[String],
+    hmac_key: Option<&[u8]>,
+) -> Result<TreeReport, String> {
+    // the messages of each metafeed, in sequence order (more than one at a fork)
+    let mut by_metafeed: BTreeMap<String, BTreeMap<u64, Vec<BendyButtMsg>>> = BTreeMap::new();
+    for msg_bytes in metafeed_msgs {
+        let msg =
+            bendybutt::verify(msg_bytes, hmac_key).map_err(|e| feed::invalid(e, msg_bytes))?;
+        let msgs = by_metafeed
+            .entry(msg.author_id())
+            .or_default()
+            .entry(msg.sequence)
+            .or_default();
+        // exact duplicates are only applied once
+        if !msgs.iter().any(|other| other.hash == msg.hash) {
+            msgs.push(msg);
+        }
+    }
+
+    let mut resolver = Resolver {
+        subfeeds: by_metafeed
+            .keys()
+            .map(|id| (id.clone(), Vec::new()))
+            .collect(),
+        inconsistencies: Vec::new(),
+    };
+    for (metafeed, msgs) in &by_metafeed {
+        let mut previous: Option<&BendyButtMsg> = None;
+        for (sequence, msgs) in msgs {
+            if let [msg, other, ..] = &msgs[..] {
+                let reason = format!(
+                    "forks at sequence {} ({} and {}), so later messages are not applied",
+                    sequence,
+                    msg.key(),
+                    other.key()
+                );
+                resolver.inconsistent(metafeed, reason);
+                break;
+            }
+            let msg = &msgs[0];
+            let expected = previous.map_or(1, |previous| previous.sequence + 1);
+            if msg.sequence != expected {
+                let reason = format!(
+                    "messages {} to {} were not given",
+                    expected,
+                    msg.sequence - 1
+                );
+                resolver.inconsistent(metafeed, reason);
+            } else if let Err(e) = bendybutt::validate_link(msg, previous) {
+                let reason = format!("{}: {}, so later messages are not applied", msg.key(), e);
+                resolver.inconsistent(metafeed, reason);
+                break;
+            }
+            if let Some(content) = &msg.content {
+                resolver.apply(metafeed, msg.key(), content);
+            }
+            previous = Some(msg);
+        }
+    }
+
+    // match the announcement of each main feed against the metafeed it names
+    let mut announced_by: HashMap<String, BTreeSet<String>> = HashMap::new();
+    for msg in main_feed_msgs {
+        let fields: AnnounceFields = serde_json::from_str(msg)
+            .map_err(|e| format!("found invalid message: {}: {}", e, msg))?;
+        if fields.content.get("type").and_then(|t| t.as_str()) != Some(ANNOUNCE) {
+            continue;
+        }
+        let metafeed = match fields.content.get("metafeed").and_then(|m| m.as_str()) {
+            Some(metafeed) => metafeed,
+            None => {
+                let reason = format!(
+                    "{} of {}: missing metafeed",
+                    ANNOUNCE,
+                    message::key(msg.as_bytes())
+                );
+                resolver.inconsistent(&fields.author, reason);
+                continue;
+            }
+        };
+        let adds_author = resolver.subfeeds.get(metafeed).map(|subfeeds| {
+            subfeeds.iter().any(|subfeed| {
+                subfeed.id == fields.author
+                    && subfeed.kind == SubfeedKind::Existing
+                    && !subfeed.tombstoned
+            })
+        });
+        match adds_author {
+            Some(true) => {
+                announced_by
+                    .entry(metafeed.to_string())
+                    .or_default()
+                    .insert(fields.author);
+            }
+            Some(false) => resolver.inconsistent(
+                &fields.author,
+                format!("announces metafeed {} which does not add it", metafeed),
+            ),
+            None => resolver.inconsistent(
+                &fields.author,
+                format!(
+                    "announces metafeed {} whose messages were not given",
+                    metafeed
+                ),
+            ),
+        }
+    }
+
+    // every main feed added as an existing feed should announce the metafeed in turn
+    let mut unannounced = Vec::new();
+    for (metafeed, subfeeds) in &resolver.subfeeds {
+        for subfeed in subfeeds {
+            if subfeed.kind == SubfeedKind::Existing
+                && !subfeed.tombstoned
+                && FeedFormat::from_author(&subfeed.id) == Ok(FeedFormat::Classic)
+                && announced_by
+                    .get(metafeed)
+                    .filter(|main_feeds| main_feeds.contains(&subfeed.id))
+                    .is_none()
+            {
+                unannounced.push((metafeed.clone(), subfeed.id.clone()));
+            }
+        }
+    }
+    for (metafeed, main_feed) in unannounced {
+        resolver.inconsistent(
+            &metafeed,
+            format!("adds main feed {} which does not announce it", main_feed),
+        );
+    }
+
+    // the roots are the metafeeds which no other given metafeed adds
+    let nested: BTreeSet<String> = resolver
+        .subfeeds
+        .values()
+        .flatten()
+        .map(|subfeed| subfeed.id.clone())
+        .collect();
+    let mut report = TreeReport::default();
+    for metafeed in by_metafeed.keys().filter(|id| !nested.contains(*id)) {
+        report
+            .roots
+            .push(resolver.build(metafeed, &announced_by, &mut Vec::new()));
+    }
+    // metafeeds which only add each other are not reached from any root (a built metafeed is
+    // removed from the resolver)
+    for metafeed in by_metafeed.keys() {
+        if resolver.subfeeds.contains_key(metafeed) {
+            resolver.inconsistent(
+                metafeed,
+                "is not reached from any root metafeed".to_string(),
+            );
+            report
+                .roots
+                .push(resolver.build(metafeed, &announced_by, &mut Vec::new()));
+        }
+    }
+    report.inconsistencies = resolver.inconsistencies;
+    Ok(report)
+}
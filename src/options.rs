@@ -0,0 +1,226 @@
+// SPDX-FileCopyrightText: 2021 Andrew 'glyph' Reid
+//
+// SPDX-License-Identifier: LGPL-3.0-only
+
+use serde::Deserialize;
+use wasm_bindgen::JsValue;
+
+use crate::{
+    filter::AuthorFilter, fork::ForkPolicy, limits::Limits, rules::Rules,
+    timestamp::TimestampPolicy,
+};
+
+/// How the messages of a batch relate to each other, which determines how they are validated.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum Ordering {
+    /// A contiguous run of a single feed, in sequence order (see `validateBatch`).
+    Ordered,
+    /// Messages of a single feed in any order, possibly with gaps (see `validateOOOBatch`).
+    OutOfOrder,
+    /// Messages of any number of feeds in any order (see `validateMultiAuthorBatch`).
+    MultiAuthor,
+    /// Messages of any number of feeds in any order, validated as strictly as the sequence
+    /// numbers of each feed allow.
+    Auto,
+}
+
+impl Ordering {
+    /// The name of the ordering, as given in the options.
+    pub fn name(self) -> &'static str {
+        match self {
+            Ordering::Ordered => "ordered",
+            Ordering::OutOfOrder => "outOfOrder",
+            Ordering::MultiAuthor => "multiAuthor",
+            Ordering::Auto => "auto",
+        }
+    }
+}
+
+/// The form of the result of a successful validation.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Output {
+    /// The array of keys alone.
+    Keys,
+    /// The full report (`{ keys, ... }`).
+    Report,
+}
+
+/// Options for validating a batch of messages, covering every entry point.
+///
+/// Options which only apply to one ordering are rejected for the others.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ValidationOptions {
+    /// How the messages relate to each other (`Ordering::Ordered` if `None`).
+    pub ordering: Option<Ordering>,
+    /// Whether to stop at the first invalid message (`true` if `None`), rather than validate
+    /// every message and report the invalid ones.
+    pub fail_fast: Option<bool>,
+    /// The form of the result (the report only when a part of it was requested, if `None`).
+    pub output: Option<Output>,
+    /// Bounds on the size of the input, each in place of its default (see `Limits::defaults`).
+    pub limits: Option<Limits>,
+    /// Rules for message timestamps.
+    pub timestamp_policy: Option<TimestampPolicy>,
+    /// What to do with forks (see `ValidationOptions::forks`).
+    pub forks: Option<ForkPolicy>,
+    /// Ordered only: the (serialized) previous message or `{ key, sequence, author }` reference.
+    pub previous: Option<String>,
+    /// Ordered only: the individual checks to apply (all of them if `None`).
+    pub rules: Option<Rules>,
+    /// Ordered only: sort the messages by sequence number before validation.
+    #[serde(default)]
+    pub auto_sort: bool,
+    /// Ordered only: trust the first message as a checkpoint.
+    #[serde(default)]
+    pub checkpoint: bool,
+    /// Out-of-order only: check the links between consecutive messages in the batch.
+    #[serde(default)]
+    pub check_links: bool,
+    /// Out-of-order only: report the sequence coverage of the batch.
+    #[serde(default)]
+    pub report_gaps: bool,
+    /// Out-of-order, multi-author and auto only: report duplicates rather than a key per message.
+    #[serde(default)]
+    pub dedupe: bool,
+    /// Multi-author only: validate the hash chain of each author.
+    #[serde(default)]
+    pub chain_per_author: bool,
+    /// Multi-author only: allowed and blocked authors.
+    pub author_filter: Option<AuthorFilter>,
+}
+
+impl ValidationOptions {
+    /// Deserialize validation options passed in from JS (`null` or `undefined` for the
+    /// defaults) and check that they can be combined.
+    pub fn from_js(value: JsValue) -> Result<ValidationOptions, String> {
+        if value.is_null() || value.is_undefined() {
+            return Ok(ValidationOptions::default());
+        }
+        let options: ValidationOptions = value
+            .into_serde()
+            .map_err(|e| format!("options invalid: {}", e))?;
+        options.check()?;
+        Ok(options)
+    }
+
+    pub fn ordering(&self) -> Ordering {
+        self.ordering.unwrap_or(Ordering::Ordered)
+    }
+
+    pub fn fail_fast(&self) -> bool {
+        self.fail_fast.unwrap_or(true)
+    }
+
+    /// The limits to enforce: those given, with the default of each limit left out.
+    pub fn limits(&self) -> Limits {
+        self.limits.unwrap_or_default().or_defaults()
+    }
+
+    /// What to do with forks, if not given: an ordered feed cannot hold a fork, and one would be
+    /// mistaken for a sparse feed by ordering `"auto"`, so both fail on forks, while the messages
+    /// of an out-of-order or multi-author batch are each validated on their own.
+    pub fn forks(&self) -> ForkPolicy {
+        self.forks.unwrap_or(match self.ordering() {
+            Ordering::Ordered | Ordering::Auto => ForkPolicy::Error,
+            Ordering::OutOfOrder | Ordering::MultiAuthor => ForkPolicy::Ignore,
+        })
+    }
+
+    /// The names of the options which are set.
+    fn set_options(&self) -> Vec<&'static str> {
+        let options = [
+            ("timestampPolicy", self.timestamp_policy.is_some()),
+            ("forks", self.forks.is_some()),
+            ("previous", self.previous.is_some()),
+            ("rules", self.rules.is_some()),
+            ("autoSort", self.auto_sort),
+            ("checkpoint", self.checkpoint),
+            ("checkLinks", self.check_links),
+            ("reportGaps", self.report_gaps),
+            ("dedupe", self.dedupe),
+            ("chainPerAuthor", self.chain_per_author),
+            ("authorFilter", self.author_filter.is_some()),
+        ];
+        options
+            .iter()
+            .filter(|(_, set)| *set)
+            .map(|(name, _)| *name)
+            .collect()
+    }
+
+    /// Check that the options apply to the ordering and can be combined with each other.
+    pub fn check(&self) -> Result<(), String> {
+        if let Some(policy) = &self.timestamp_policy {
+            policy.check()?;
+        }
+
+        let ordering = self.ordering();
+        for name in self.set_options() {
+            let applies = match name {
+                "previous" | "autoSort" | "checkpoint" => ordering == Ordering::Ordered,
+                "rules" => ordering != Ordering::Auto,
+                "checkLinks" | "reportGaps" => ordering == Ordering::OutOfOrder,
+                "dedupe" => ordering != Ordering::Ordered,
+                "chainPerAuthor" | "authorFilter" => ordering == Ordering::MultiAuthor,
+                _ => true,
+            };
+            if !applies {
+                return Err(format!(
+                    "options invalid: `{}` does not apply to ordering \"{}\"",
+                    name,
+                    ordering.name()
+                ));
+            }
+        }
+
+        // the hash chain of each author is only validated in full
+        if self.chain_per_author
+            && self
+                .rules
+                .filter(|rules| !rules.full_validation())
+                .is_some()
+        {
+            return Err(
+                "options invalid: `chainPerAuthor` cannot be combined with `rules`".to_string(),
+            );
+        }
+
+        if self.checkpoint && self.previous.is_some() {
+            return Err(
+                "checkpoint invalid: cannot be combined with a previous message".to_string(),
+            );
+        }
+
+        if !self.fail_fast() {
+            // invalid messages are reported individually, so only the checks made one message
+            // at a time are available
+            let batch_options = ["previous", "autoSort", "checkpoint"];
+            if let Some(name) = self
+                .set_options()
+                .into_iter()
+                .find(|name| !batch_options.contains(name))
+            {
+                return Err(format!(
+                    "options invalid: `{}` cannot be combined with `failFast: false`",
+                    name
+                ));
+            }
+            if self.ordering() == Ordering::Auto {
+                return Err(
+                    "options invalid: ordering \"auto\" cannot be combined with `failFast: false`"
+                        .to_string(),
+                );
+            }
+            if self.output == Some(Output::Keys) {
+                return Err(
+                    "options invalid: `failFast: false` requires the report output".to_string(),
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
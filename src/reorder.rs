@@ -0,0 +1,349 @@
+// SPDX-FileCopyrightText: 2021 Andrew 'glyph' Reid
+//
+// SPDX-License-Identifier: LGPL-3.0-only
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    fork::Fork,
+    hmac::HmacKey,
+    limits::{self, Limits},
+    message,
+    state::{validate_link, verify_message, FeedState, FeedTip},
+};
+
+/// The number of messages held in a buffer at most, unless set with `setMaxPending`.
+const DEFAULT_MAX_PENDING: usize = 10_000;
+
+/// Position of a message held in the buffer while waiting for its predecessor.
+#[derive(Debug, Serialize)]
+pub struct PendingMessage {
+    pub author: String,
+    pub sequence: u64,
+}
+
+/// A buffered message which failed validation when it was released.
+#[derive(Debug, Serialize)]
+pub struct RejectedMessage {
+    pub author: String,
+    pub sequence: u64,
+    pub error: String,
+}
+
+/// Outcome of pushing messages into (or seeding) a `ReorderBuffer`.
+#[derive(Debug, Default, Serialize)]
+pub struct ReorderResult {
+    /// Keys of the messages released and chain-validated by this push, in feed order.
+    pub validated: Vec<String>,
+    /// Messages still waiting for their predecessor to arrive.
+    pub pending: Vec<PendingMessage>,
+    /// Messages dropped by this push to keep the buffer within its maximum size.
+    pub evicted: Vec<PendingMessage>,
+    /// Messages dropped by this push because they did not extend their feed when released.
+    pub invalid: Vec<RejectedMessage>,
+}
+
+/// Buffer for out-of-order messages which releases and validates each message once its
+/// predecessor is known.
+///
+/// Signatures and message values are verified when a message is pushed, while the hash-chain
+/// link (author, sequence and previous key) is checked when the message is released. A message
+/// which fails that check is dropped and reported, so that a valid message may still take its
+/// place. Messages at or below the tip of their feed have already been validated and are
+/// dropped, unless they conflict with the tip itself (a fork).
+///
+/// The buffer holds at most `DEFAULT_MAX_PENDING` messages (or as set with `setMaxPending`).
+/// Beyond that, the messages furthest ahead of the tip of their feed are evicted first, being
+/// the least likely to be released soon.
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct ReorderBuffer {
+    tips: HashMap<String, FeedTip>,
+    pending: HashMap<String, BTreeMap<u64, Vec<u8>>>,
+    max_pending: Option<usize>,
+}
+
+#[wasm_bindgen]
+impl ReorderBuffer {
+    /// Create an empty buffer (no known feeds).
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> ReorderBuffer {
+        ReorderBuffer::default()
+    }
+
+    /// Set the tip of a feed, allowing messages after `sequence` to be released without first
+    /// pushing the earlier history of the feed.
+    #[wasm_bindgen(js_name = setTip)]
+    pub fn set_tip(&mut self, author: String, sequence: u64, key: String) {
+        self.tips.insert(
+            author.clone(),
+            FeedTip {
+                author,
+                sequence,
+                key,
+            },
+        );
+    }
+
+    /// Take the tip of every feed known to the given state which is ahead of the tip known to
+    /// the buffer, dropping the pending messages at or below it, then release every pending
+    /// message which now follows its tip. Returns the result as for `push`.
+    #[wasm_bindgen(js_name = seedFromState)]
+    pub fn seed_from_state(&mut self, state: &FeedState) -> JsValue {
+        let mut seeded = Vec::new();
+        for tip in state.tips() {
+            if let Some(known) = self.tips.get(&tip.author) {
+                if known.sequence >= tip.sequence {
+                    continue;
+                }
+            }
+            if let Some(msgs) = self.pending.get_mut(&tip.author) {
+                match tip.sequence.checked_add(1) {
+                    Some(next_sequence) => *msgs = msgs.split_off(&next_sequence),
+                    None => msgs.clear(),
+                }
+                if msgs.is_empty() {
+                    self.pending.remove(&tip.author);
+                }
+            }
+            self.tips.insert(tip.author.clone(), tip.clone());
+            seeded.push(tip.author.clone());
+        }
+
+        let result = self.release_all(&seeded);
+        let response: (Option<String>, Option<ReorderResult>) = (None, Some(result));
+        JsValue::from_serde(&response).expect("failed to serialize response with released keys")
+    }
+
+    /// Set the number of messages held in the buffer at most. Messages beyond it are evicted
+    /// by the next push.
+    #[wasm_bindgen(js_name = setMaxPending)]
+    pub fn set_max_pending(&mut self, max_pending: usize) {
+        self.max_pending = Some(max_pending);
+    }
+
+    /// Verify and buffer an array of messages (includes HMAC key support), then release and
+    /// chain-validate every message whose predecessor is now known.
+    ///
+    /// Messages may be by multiple authors and in any order. If verification of any message
+    /// fails, or it forks its feed, the cause of the error is returned along with the offending
+    /// message and the buffer is left unchanged. Otherwise the keys of the released messages are
+    /// returned, along with the messages which are still pending, those evicted to keep the
+    /// buffer within its maximum size and those dropped as `invalid` when released.
+    pub fn push(&mut self, hmac_key: &HmacKey, array: JsValue) -> JsValue {
+        let hmac = match hmac_key.single() {
+            Ok(hmac) => hmac,
+            Err(err_msg) => {
+                let response: (Option<String>, Option<ReorderResult>) = (Some(err_msg), None);
+                return JsValue::from_serde(&response)
+                    .expect("failed to serialize response for invalid hmac key error");
+            }
+        };
+
+        let elements = match limits::read_messages(&array, &Limits::defaults()) {
+            Ok(elements) => elements,
+            Err(err_msg) => return crate::error_response(err_msg),
+        };
+
+        // stage the pending messages of every affected feed so that a failure leaves the
+        // buffer untouched
+        let mut staged: HashMap<String, BTreeMap<u64, Vec<u8>>> = HashMap::new();
+        for msg in elements {
+            let msg_bytes = msg.into_bytes();
+            let meta = match verify_message(&msg_bytes, hmac) {
+                Ok(meta) => meta,
+                Err(e) => {
+                    let err_msg = format!(
+                        "found invalid message: {}: {}",
+                        e,
+                        message::as_str(&msg_bytes)
+                    );
+                    let response: (Option<String>, Option<ReorderResult>) = (Some(err_msg), None);
+                    return JsValue::from_serde(&response)
+                        .expect("failed to serialize response with invalid message error");
+                }
+            };
+            if let Some(tip) = self.tips.get(&meta.author) {
+                // a different message at the tip is a fork
+                if meta.sequence == tip.sequence {
+                    let key = message::key(&msg_bytes);
+                    if key != tip.key {
+                        let fork = Fork {
+                            author: meta.author,
+                            sequence: meta.sequence,
+                            keys: [tip.key.clone(), key],
+                        };
+                        let response: (Option<String>, Option<ReorderResult>) =
+                            (Some(fork.to_string()), None);
+                        return JsValue::from_serde(&response)
+                            .expect("failed to serialize response with fork error");
+                    }
+                }
+                if meta.sequence <= tip.sequence {
+                    continue;
+                }
+            }
+            let pending = self.pending.get(&meta.author);
+            let msgs = staged
+                .entry(meta.author.clone())
+                .or_insert_with(|| pending.cloned().unwrap_or_default());
+            // a different message already waiting at the same position is also a fork
+            if let Some(buffered) = msgs.get(&meta.sequence) {
+                if *buffered != msg_bytes {
+                    let fork = Fork {
+                        author: meta.author,
+                        sequence: meta.sequence,
+                        keys: [message::key(buffered), message::key(&msg_bytes)],
+                    };
+                    let response: (Option<String>, Option<ReorderResult>) =
+                        (Some(fork.to_string()), None);
+                    return JsValue::from_serde(&response)
+                        .expect("failed to serialize response with fork error");
+                }
+            }
+            msgs.insert(meta.sequence, msg_bytes);
+        }
+
+        // commit the staged messages, then release those which follow the tip of their feed
+        let authors: Vec<String> = staged.keys().cloned().collect();
+        for (author, msgs) in staged {
+            if msgs.is_empty() {
+                self.pending.remove(&author);
+            } else {
+                self.pending.insert(author, msgs);
+            }
+        }
+        let result = self.release_all(&authors);
+
+        let response: (Option<String>, Option<ReorderResult>) = (None, Some(result));
+        JsValue::from_serde(&response)
+            .expect("failed to serialize response with successfully verified keys")
+    }
+
+    /// Return the messages still waiting for their predecessor (`[{ author, sequence }]`).
+    pub fn pending(&self) -> JsValue {
+        JsValue::from_serde(&self.pending_messages()).expect("failed to serialize pending messages")
+    }
+}
+
+impl ReorderBuffer {
+    /// Release the pending messages of the given feeds, then evict the messages beyond the
+    /// maximum size of the buffer.
+    fn release_all(&mut self, authors: &[String]) -> ReorderResult {
+        let mut result = ReorderResult::default();
+        for author in authors {
+            self.release(author, &mut result);
+        }
+        result.evicted = self.evict();
+        result.pending = self.pending_messages();
+        result
+    }
+
+    /// Chain-validate and release every pending message of a feed which follows its tip. A
+    /// message which fails validation is dropped, leaving its position open for a valid message
+    /// and the messages after it pending.
+    fn release(&mut self, author: &str, result: &mut ReorderResult) {
+        let msgs = match self.pending.get_mut(author) {
+            Some(msgs) => msgs,
+            None => return,
+        };
+        let mut tip = self.tips.get(author).cloned();
+        loop {
+            let next_sequence = match &tip {
+                Some(tip) => match tip.sequence.checked_add(1) {
+                    Some(next_sequence) => next_sequence,
+                    None => break,
+                },
+                None => 1,
+            };
+            let msg_bytes = match msgs.remove(&next_sequence) {
+                Some(msg_bytes) => msg_bytes,
+                None => break,
+            };
+            match validate_link(&msg_bytes, tip.as_ref()) {
+                Ok(new_tip) => {
+                    result.validated.push(new_tip.key.clone());
+                    tip = Some(new_tip);
+                }
+                Err(e) => {
+                    result.invalid.push(RejectedMessage {
+                        author: author.to_string(),
+                        sequence: next_sequence,
+                        error: format!(
+                            "found invalid message: {}: {}",
+                            e,
+                            message::as_str(&msg_bytes)
+                        ),
+                    });
+                    break;
+                }
+            }
+        }
+        if msgs.is_empty() {
+            self.pending.remove(author);
+        }
+        if let Some(tip) = tip {
+            self.tips.insert(author.to_string(), tip);
+        }
+    }
+
+    /// Evict the messages beyond the maximum size of the buffer, furthest ahead of the tip of
+    /// their feed first.
+    fn evict(&mut self) -> Vec<PendingMessage> {
+        let max_pending = self.max_pending.unwrap_or(DEFAULT_MAX_PENDING);
+        let total: usize = self.pending.values().map(BTreeMap::len).sum();
+        if total <= max_pending {
+            return Vec::new();
+        }
+
+        let tips = &self.tips;
+        let mut by_distance: Vec<(u64, String, u64)> = self
+            .pending
+            .iter()
+            .flat_map(|(author, msgs)| {
+                let next_sequence = tips
+                    .get(author)
+                    .map_or(1, |tip| tip.sequence.saturating_add(1));
+                msgs.keys().map(move |sequence| {
+                    (
+                        sequence.saturating_sub(next_sequence),
+                        author.clone(),
+                        *sequence,
+                    )
+                })
+            })
+            .collect();
+        by_distance.sort_by(|a, b| b.cmp(a));
+
+        let mut evicted = Vec::new();
+        for (_, author, sequence) in by_distance.into_iter().take(total - max_pending) {
+            if let Some(msgs) = self.pending.get_mut(&author) {
+                msgs.remove(&sequence);
+                if msgs.is_empty() {
+                    self.pending.remove(&author);
+                }
+            }
+            evicted.push(PendingMessage { author, sequence });
+        }
+        evicted.sort_by(|a, b| (&a.author, a.sequence).cmp(&(&b.author, b.sequence)));
+        evicted
+    }
+
+    fn pending_messages(&self) -> Vec<PendingMessage> {
+        let mut pending: Vec<PendingMessage> = self
+            .pending
+            .iter()
+            .flat_map(|(author, msgs)| {
+                msgs.keys().map(move |sequence| PendingMessage {
+                    author: author.clone(),
+                    sequence: *sequence,
+                })
+            })
+            .collect();
+        pending.sort_by(|a, b| (&a.author, a.sequence).cmp(&(&b.author, b.sequence)));
+        pending
+    }
+}
@@ -0,0 +1,53 @@
+// SPDX-FileCopyrightText: 2021 Andrew 'glyph' Reid
+//
+// SPDX-License-Identifier: LGPL-3.0-only
+
+use serde::Serialize;
+
+use crate::{chain::ChainGroup, fork::Fork, gaps::FeedGaps};
+
+/// A message which failed verification or validation, when every message is validated.
+#[derive(Debug, Serialize)]
+pub struct InvalidMessage {
+    /// Position of the message in the batch.
+    pub position: usize,
+    /// The cause of the error, along with the message.
+    pub error: String,
+}
+
+/// Detailed result of a successful batch validation, returned in place of the array of keys
+/// when a report is requested.
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchReport {
+    pub keys: Vec<String>,
+    /// Sequence coverage of each feed in the batch.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub feeds: Option<Vec<FeedGaps>>,
+    /// Validation mode applied to each feed of the batch, when the mode is detected
+    /// automatically.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub groups: Option<Vec<ChainGroup>>,
+    /// Positions of the batch holding a copy of an earlier message.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duplicates: Option<Vec<usize>>,
+    /// Positions of the batch holding a message of a disallowed author, which was skipped.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skipped: Option<Vec<usize>>,
+    /// Sequence number of the trusted checkpoint the chain was validated from, when not
+    /// validated from the start of the feed or a known previous message.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub anchored_at: Option<u64>,
+    /// Index of the HMAC key each message verified with, when a list of keys was given.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub matched_keys: Option<Vec<usize>>,
+    /// Messages which failed verification or validation, when every message is validated.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub invalid: Option<Vec<InvalidMessage>>,
+    /// Forks found in the batch, when reported rather than failed on.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub forks: Option<Vec<Fork>>,
+    /// Violations of warning-only timestamp rules.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warnings: Option<Vec<String>>,
+}
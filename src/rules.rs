@@ -0,0 +1,258 @@
+// SPDX-FileCopyrightText: 2021 Andrew 'glyph' Reid
+//
+// SPDX-License-Identifier: LGPL-3.0-only
+
+use std::fmt;
+
+use serde::{
+    de::{IgnoredAny, MapAccess, Visitor},
+    Deserialize, Deserializer,
+};
+use ssb_validate::message_value::validate_message_value;
+
+use crate::{message, state::FeedTip};
+
+/// The maximum length of a message value, in UTF-16 code units of its serialized form.
+const MAX_MESSAGE_LENGTH: usize = 8192;
+
+/// The order of the fields of a message value; early feeds have `sequence` before `author`.
+const FIELD_ORDER: [&str; 7] = [
+    "previous",
+    "author",
+    "sequence",
+    "timestamp",
+    "hash",
+    "content",
+    "signature",
+];
+const LEGACY_FIELD_ORDER: [&str; 7] = [
+    "previous",
+    "sequence",
+    "author",
+    "timestamp",
+    "hash",
+    "content",
+    "signature",
+];
+
+/// The individual checks applied to a batch, each enabled unless set to `false`.
+///
+/// Disabling a rule leaves out its own check only: every other check of `ssb_validate` still
+/// applies. The `link` and `sequence` rules relate a message to its predecessor, so they are only
+/// checked in an ordered batch; `author` is checked against the predecessor in an ordered batch
+/// and against the first message in an out-of-order batch.
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Rules {
+    /// The signature of each message verifies (with the given HMAC key or keys).
+    pub signature: bool,
+    /// The `previous` field of each message holds the key of the message before it.
+    pub link: bool,
+    /// The sequence number of each message is one greater than that of the message before it.
+    pub sequence: bool,
+    /// Each message has the same author as the message before it.
+    pub author: bool,
+    /// The `hash` field of each message is `sha256`.
+    pub hash: bool,
+    /// Each serialized message is no longer than 8192 UTF-16 code units.
+    pub length: bool,
+}
+
+impl Default for Rules {
+    fn default() -> Rules {
+        Rules {
+            signature: true,
+            link: true,
+            sequence: true,
+            author: true,
+            hash: true,
+            length: true,
+        }
+    }
+}
+
+impl Rules {
+    /// Whether every rule other than `signature` is enabled, in which case the validation of
+    /// `ssb_validate` applies unchanged (whether or not signatures are verified).
+    pub fn full_validation(&self) -> bool {
+        self.link && self.sequence && self.author && self.hash && self.length
+    }
+}
+
+/// The message value fields checked by the rules.
+#[derive(Deserialize)]
+struct RuleFields {
+    author: String,
+    sequence: u64,
+    previous: Option<String>,
+}
+
+fn parse_fields(msg: &[u8]) -> Result<RuleFields, String> {
+    serde_json::from_slice(msg).map_err(|e| format!("failed to parse message value fields: {}", e))
+}
+
+/// Every field of a message value, to check that each is present and of the right type.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+#[allow(dead_code)]
+struct MessageValue {
+    previous: Option<String>,
+    author: String,
+    sequence: u64,
+    timestamp: f64,
+    hash: String,
+    content: serde_json::Value,
+    signature: String,
+}
+
+/// The keys of a JSON object, in the order they appear.
+struct Keys(Vec<String>);
+
+impl<'de> Deserialize<'de> for Keys {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Keys, D::Error> {
+        struct KeysVisitor;
+
+        impl<'de> Visitor<'de> for KeysVisitor {
+            type Value = Keys;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a message value object")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Keys, A::Error> {
+                let mut keys = Vec::new();
+                while let Some(key) = map.next_key::<String>()? {
+                    map.next_value::<IgnoredAny>()?;
+                    keys.push(key);
+                }
+                Ok(Keys(keys))
+            }
+        }
+
+        deserializer.deserialize_map(KeysVisitor)
+    }
+}
+
+/// Check a message value on its own, as `ssb_validate` does, leaving out the `hash` and
+/// `length` checks if their rules are disabled.
+pub fn check_value(msg: &[u8], rules: &Rules) -> Result<(), String> {
+    if rules.hash && rules.length {
+        return validate_message_value(msg).map_err(|e| e.to_string());
+    }
+
+    // `ssb_validate` cannot leave out a single check, so its other message value checks are
+    // made here: field types, field order and content
+    let value: MessageValue =
+        serde_json::from_slice(msg).map_err(|e| format!("failed to parse message value: {}", e))?;
+    let Keys(keys) =
+        serde_json::from_slice(msg).map_err(|e| format!("failed to parse message value: {}", e))?;
+    if keys != FIELD_ORDER && keys != LEGACY_FIELD_ORDER {
+        return Err(format!(
+            "fields of message value are not in the order {}",
+            FIELD_ORDER.join(", ")
+        ));
+    }
+    check_content(&value.content)?;
+
+    if rules.hash && value.hash != "sha256" {
+        return Err(format!("hash of message ({}) must be sha256", value.hash));
+    }
+    if rules.length {
+        // measured on the message as given, which parsing has shown to be valid UTF-8
+        let length = std::str::from_utf8(msg)
+            .map_err(|e| format!("message value is not valid utf8: {}", e))?
+            .encode_utf16()
+            .count();
+        if length > MAX_MESSAGE_LENGTH {
+            return Err(format!(
+                "length of message ({}) exceeds the maximum of {}",
+                length, MAX_MESSAGE_LENGTH
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Check the content of a message value: an object with a `type` of 3 to 52 characters, or an
+/// encrypted (`.box`) string.
+fn check_content(content: &serde_json::Value) -> Result<(), String> {
+    match content {
+        serde_json::Value::Object(fields) => match fields.get("type") {
+            Some(serde_json::Value::String(content_type))
+                if (3..=52).contains(&content_type.chars().count()) =>
+            {
+                Ok(())
+            }
+            _ => Err("content of message must have a type of 3 to 52 characters".to_string()),
+        },
+        serde_json::Value::String(encrypted)
+            if encrypted
+                .trim_end_matches(|c: char| c.is_ascii_digit())
+                .ends_with(".box") =>
+        {
+            Ok(())
+        }
+        _ => Err("content of message must be an object or an encrypted string".to_string()),
+    }
+}
+
+/// Check that a message has the same author as the first message of its batch, whose author is
+/// remembered in `feed_author`.
+pub fn check_same_author(msg: &[u8], feed_author: &mut Option<String>) -> Result<(), String> {
+    let fields = parse_fields(msg)?;
+    match feed_author {
+        Some(author) if *author != fields.author => Err(format!(
+            "author of message ({}) did not match author of first message ({})",
+            fields.author, author
+        )),
+        Some(_) => Ok(()),
+        None => {
+            *feed_author = Some(fields.author);
+            Ok(())
+        }
+    }
+}
+
+/// Check that a message extends a feed with the given tip (if any) according to the enabled
+/// rules which relate it to its predecessor (`author`, `sequence` and `link`). Returns the new
+/// tip of the feed.
+pub fn check_link(msg: &[u8], tip: Option<&FeedTip>, rules: &Rules) -> Result<FeedTip, String> {
+    let fields = parse_fields(msg)?;
+    match tip {
+        None => {
+            if rules.sequence && fields.sequence != 1 {
+                return Err("The first message of a feed must have seq of 1".to_string());
+            }
+            if rules.link && fields.previous.is_some() {
+                return Err("The first message of a feed must have previous of null".to_string());
+            }
+        }
+        Some(tip) => {
+            if rules.author && fields.author != tip.author {
+                return Err(format!(
+                    "author of message ({}) did not match author of previous message ({})",
+                    fields.author, tip.author
+                ));
+            }
+            if rules.sequence && tip.sequence.checked_add(1) != Some(fields.sequence) {
+                return Err(format!(
+                    "sequence of message ({}) is not one greater than that of previous message ({})",
+                    fields.sequence, tip.sequence
+                ));
+            }
+            if rules.link && fields.previous.as_ref() != Some(&tip.key) {
+                return Err(format!(
+                    "previous of message ({}) did not match key of previous message ({})",
+                    fields.previous.unwrap_or_else(|| "null".to_string()),
+                    tip.key
+                ));
+            }
+        }
+    }
+
+    Ok(FeedTip {
+        author: fields.author,
+        sequence: fields.sequence,
+        key: message::key(msg),
+    })
+}
@@ -0,0 +1,336 @@
+// SPDX-FileCopyrightText: 2021 Andrew 'glyph' Reid
+//
+// SPDX-License-Identifier: LGPL-3.0-only
+
+use std::collections::HashMap;
+use std::convert::TryInto;
+
+use serde::{Deserialize, Serialize};
+use ssb_validate::message_value::validate_ooo_message_value_hash_chain;
+use ssb_verify_signatures::verify_message_value;
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    fork::Fork,
+    hmac::HmacKey,
+    limits::{self, Limits},
+    message::{self, MessageMeta},
+};
+
+/// Version byte written at the start of every snapshot.
+const SNAPSHOT_VERSION: u8 = 1;
+
+/// The latest validated message of a feed: the minimum required to validate the next message.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FeedTip {
+    pub author: String,
+    pub sequence: u64,
+    pub key: String,
+}
+
+impl FeedTip {
+    /// Create the tip of a feed from its latest message.
+    pub fn from_message(msg: &[u8]) -> Result<FeedTip, String> {
+        let meta = message::parse_meta(msg)?;
+        Ok(FeedTip {
+            author: meta.author,
+            sequence: meta.sequence,
+            key: message::key(msg),
+        })
+    }
+}
+
+/// The predecessor of a message (or batch of messages): either the full previous message value
+/// or a lightweight reference to it.
+pub enum Previous {
+    Message(Vec<u8>),
+    Tip(FeedTip),
+}
+
+impl Previous {
+    /// Interpret the previous argument passed in from JS, which is either a message value or a
+    /// `{ key, sequence, author }` reference. A message value has no `key` field and therefore
+    /// never deserializes as a reference.
+    pub fn parse(previous: Vec<u8>) -> Previous {
+        match serde_json::from_slice::<FeedTip>(&previous) {
+            Ok(tip) => Previous::Tip(tip),
+            Err(_) => Previous::Message(previous),
+        }
+    }
+
+    /// The tip of the feed described by this predecessor.
+    pub fn tip(&self) -> Result<FeedTip, String> {
+        match self {
+            Previous::Message(msg) => FeedTip::from_message(msg),
+            Previous::Tip(tip) => Ok(tip.clone()),
+        }
+    }
+}
+
+/// Verify the signature of a message and validate its value, without reference to any other
+/// message of the feed. Returns the fields required to place the message in its feed.
+pub fn verify_message(msg: &[u8], hmac: Option<&[u8]>) -> Result<MessageMeta, String> {
+    verify_message_value(msg, hmac).map_err(|e| e.to_string())?;
+    validate_ooo_message_value_hash_chain::<_, &[u8]>(msg, None).map_err(|e| e.to_string())?;
+    message::parse_meta(msg)
+}
+
+/// Validate a message value and check that it correctly extends a feed with the given tip.
+/// Signature verification must be performed by the caller.
+pub fn validate_against_tip(msg: &[u8], tip: &FeedTip) -> Result<FeedTip, String> {
+    validate_ooo_message_value_hash_chain::<_, &[u8]>(msg, None).map_err(|e| e.to_string())?;
+    validate_link(msg, Some(tip))
+}
+
+/// Check that a message correctly extends a feed, given the tip of that feed (if any).
+///
+/// Only the hash-chain link is checked here (author, sequence and previous key); signature
+/// verification and message value validation must be performed by the caller. Returns the
+/// new tip of the feed if the link is valid.
+pub fn validate_link(msg: &[u8], tip: Option<&FeedTip>) -> Result<FeedTip, String> {
+    let meta = message::parse_meta(msg)?;
+    match tip {
+        None => {
+            if meta.sequence != 1 {
+                return Err("The first message of a feed must have seq of 1".to_string());
+            }
+            if meta.previous.is_some() {
+                return Err("The first message of a feed must have previous of null".to_string());
+            }
+        }
+        Some(tip) => {
+            if meta.author != tip.author {
+                return Err(format!(
+                    "author of message ({}) did not match author of previous message ({})",
+                    meta.author, tip.author
+                ));
+            }
+            if meta.sequence == tip.sequence {
+                let key = message::key(msg);
+                if key != tip.key {
+                    return Err(Fork {
+                        author: meta.author,
+                        sequence: meta.sequence,
+                        keys: [tip.key.clone(), key],
+                    }
+                    .to_string());
+                }
+            }
+            if meta.sequence != tip.sequence + 1 {
+                return Err(format!(
+                    "sequence of message ({}) is not one greater than that of previous message ({})",
+                    meta.sequence, tip.sequence
+                ));
+            }
+            if meta.previous.as_ref() != Some(&tip.key) {
+                return Err(format!(
+                    "previous of message ({}) did not match key of previous message ({})",
+                    meta.previous.unwrap_or_else(|| "null".to_string()),
+                    tip.key
+                ));
+            }
+        }
+    }
+
+    Ok(FeedTip {
+        author: meta.author,
+        sequence: meta.sequence,
+        key: message::key(msg),
+    })
+}
+
+/// Per-author validation state, holding the tip of every feed seen so far.
+///
+/// The state can be exported to (and restored from) a compact binary snapshot, allowing it to
+/// be persisted between sessions instead of re-reading the latest message of each feed.
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct FeedState {
+    tips: HashMap<String, FeedTip>,
+}
+
+#[wasm_bindgen]
+impl FeedState {
+    /// Create an empty state (no known feeds).
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> FeedState {
+        FeedState::default()
+    }
+
+    /// Verify signatures and perform validation for an array of messages which extend the
+    /// feeds tracked by this state (includes HMAC key support).
+    ///
+    /// Messages may be by multiple authors but must be in order for each author. The first
+    /// message of an unknown author must be the first message of the feed. The state is only
+    /// updated if every message is valid. If verification or validation fails, the cause of
+    /// the error is returned along with the offending message.
+    pub fn validate(&mut self, hmac_key: &HmacKey, array: JsValue) -> JsValue {
+        let hmac = match hmac_key.single() {
+            Ok(hmac) => hmac,
+            Err(err_msg) => {
+                let response: (Option<String>, Option<Vec<String>>) = (Some(err_msg), None);
+                return JsValue::from_serde(&response)
+                    .expect("failed to serialize response for invalid hmac key error");
+            }
+        };
+
+        let elements = match limits::read_messages(&array, &Limits::defaults()) {
+            Ok(elements) => elements,
+            Err(err_msg) => return crate::error_response(err_msg),
+        };
+
+        let mut updated: HashMap<String, FeedTip> = HashMap::new();
+        let mut keys = Vec::new();
+        for msg in elements {
+            let msg_bytes = msg.into_bytes();
+            let result = verify_message(&msg_bytes, hmac).and_then(|meta| {
+                let tip = updated
+                    .get(&meta.author)
+                    .or_else(|| self.tips.get(&meta.author));
+                validate_link(&msg_bytes, tip)
+            });
+            match result {
+                Ok(tip) => {
+                    keys.push(tip.key.clone());
+                    updated.insert(tip.author.clone(), tip);
+                }
+                Err(e) => {
+                    let err_msg = format!(
+                        "found invalid message: {}: {}",
+                        e,
+                        message::as_str(&msg_bytes)
+                    );
+                    let response: (Option<String>, Option<Vec<String>>) = (Some(err_msg), None);
+                    return JsValue::from_serde(&response)
+                        .expect("failed to serialize response with invalid message error");
+                }
+            }
+        }
+
+        self.tips.extend(updated);
+
+        let response: (Option<String>, Option<Vec<String>>) = (None, Some(keys));
+        JsValue::from_serde(&response)
+            .expect("failed to serialize response with successfully verified keys")
+    }
+
+    /// Return the tip (`{ author, sequence, key }`) of the given feed, or `null` if unknown.
+    #[wasm_bindgen(js_name = getTip)]
+    pub fn get_tip(&self, author: String) -> JsValue {
+        JsValue::from_serde(&self.tips.get(&author)).expect("failed to serialize feed tip")
+    }
+
+    /// Export the state as a binary snapshot (`Uint8Array`).
+    #[wasm_bindgen(js_name = toSnapshot)]
+    pub fn to_snapshot(&self) -> Vec<u8> {
+        encode_snapshot(&self.tips)
+    }
+
+    /// Replace the state with the contents of a binary snapshot.
+    ///
+    /// Returns the number of feeds restored, or an error if the snapshot is malformed (in which
+    /// case the existing state is left untouched).
+    #[wasm_bindgen(js_name = loadSnapshot)]
+    pub fn load_snapshot(&mut self, snapshot: &[u8]) -> JsValue {
+        let response: (Option<String>, Option<usize>) = match decode_snapshot(snapshot) {
+            Ok(tips) => {
+                self.tips = tips;
+                (None, Some(self.tips.len()))
+            }
+            Err(err_msg) => (Some(err_msg), None),
+        };
+        JsValue::from_serde(&response).expect("failed to serialize snapshot load response")
+    }
+}
+
+impl FeedState {
+    /// The tip of every known feed.
+    pub fn tips(&self) -> impl Iterator<Item = &FeedTip> {
+        self.tips.values()
+    }
+}
+
+fn push_str(buf: &mut Vec<u8>, s: &str) {
+    buf.extend_from_slice(&(s.len() as u32).to_le_bytes());
+    buf.extend_from_slice(s.as_bytes());
+}
+
+/// Encode feed tips as: version (u8), count (u32), then for each tip: author (u32 length
+/// prefix + utf8), sequence (u64) and key (u32 length prefix + utf8). Integers are little-endian
+/// and tips are sorted by author so that equal states produce equal snapshots.
+fn encode_snapshot(tips: &HashMap<String, FeedTip>) -> Vec<u8> {
+    let mut sorted: Vec<&FeedTip> = tips.values().collect();
+    sorted.sort_by(|a, b| a.author.cmp(&b.author));
+
+    let mut buf = vec![SNAPSHOT_VERSION];
+    buf.extend_from_slice(&(sorted.len() as u32).to_le_bytes());
+    for tip in sorted {
+        push_str(&mut buf, &tip.author);
+        buf.extend_from_slice(&tip.sequence.to_le_bytes());
+        push_str(&mut buf, &tip.key);
+    }
+    buf
+}
+
+/// Cursor over snapshot bytes.
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], String> {
+        if self.bytes.len() < n {
+            return Err("snapshot invalid: unexpected end of input".to_string());
+        }
+        let (head, tail) = self.bytes.split_at(n);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let len = self.u32()? as usize;
+        let bytes = self.take(len)?;
+        String::from_utf8(bytes.to_vec())
+            .map_err(|_| "snapshot invalid: string is not valid utf8".to_string())
+    }
+}
+
+fn decode_snapshot(snapshot: &[u8]) -> Result<HashMap<String, FeedTip>, String> {
+    let mut reader = Reader { bytes: snapshot };
+    let version = reader.u8()?;
+    if version != SNAPSHOT_VERSION {
+        return Err(format!("snapshot invalid: unsupported version {}", version));
+    }
+    let count = reader.u32()?;
+
+    let mut tips = HashMap::new();
+    for _ in 0..count {
+        let author = reader.string()?;
+        let sequence = reader.u64()?;
+        let key = reader.string()?;
+        tips.insert(
+            author.clone(),
+            FeedTip {
+                author,
+                sequence,
+                key,
+            },
+        );
+    }
+    if !reader.bytes.is_empty() {
+        return Err("snapshot invalid: trailing bytes".to_string());
+    }
+    Ok(tips)
+}
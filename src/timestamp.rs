@@ -0,0 +1,133 @@
+// SPDX-FileCopyrightText: 2021 Andrew 'glyph' Reid
+//
+// SPDX-License-Identifier: LGPL-3.0-only
+
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+
+use crate::message;
+
+/// How to treat timestamps which decrease within a feed.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Monotonic {
+    /// A decreasing timestamp is reported as a warning.
+    Warn,
+    /// A decreasing timestamp is a validation error.
+    Strict,
+}
+
+/// Sequence number, timestamp and bytes of each message of a feed.
+type FeedTimestamps<'a> = Vec<(u64, f64, &'a [u8])>;
+
+/// Caller-supplied rules for message timestamps.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct TimestampPolicy {
+    /// The current time (milliseconds since the Unix epoch).
+    pub now: Option<f64>,
+    /// The maximum number of milliseconds a timestamp may be ahead of `now`.
+    pub max_skew: Option<f64>,
+    /// Whether timestamps must increase within a feed (not checked if `None`).
+    pub monotonic: Option<Monotonic>,
+}
+
+impl TimestampPolicy {
+    /// Check that the rules of the policy can be applied.
+    pub fn check(&self) -> Result<(), String> {
+        if self.max_skew.is_some() && self.now.is_none() {
+            return Err("timestamp policy invalid: `maxSkew` requires `now`".to_string());
+        }
+        if matches!(self.max_skew, Some(max_skew) if max_skew < 0.0) {
+            return Err(
+                "timestamp policy invalid: `maxSkew` must be a non-negative number".to_string(),
+            );
+        }
+        Ok(())
+    }
+}
+
+#[derive(Deserialize)]
+struct TimestampMeta {
+    author: String,
+    sequence: u64,
+    timestamp: f64,
+}
+
+/// Check the timestamps of a batch of messages against a policy.
+///
+/// Timestamps are compared in sequence order within each feed, so the batch may be in any
+/// order. The message preceding the batch (if given) is only used as the first timestamp of its
+/// feed. Returns the warnings produced by warning-only rules (`None` unless `monotonic` is
+/// given), or an error for the first violation of a strict rule (along with the offending
+/// message).
+pub fn check_timestamps(
+    msgs: &[Vec<u8>],
+    previous: Option<&[u8]>,
+    policy: &TimestampPolicy,
+) -> Result<Option<Vec<String>>, String> {
+    let mut feeds: BTreeMap<String, FeedTimestamps> = BTreeMap::new();
+    // a malformed previous message is reported by the validation of the first message
+    if let Some(previous) = previous {
+        if let Ok(meta) = serde_json::from_slice::<TimestampMeta>(previous) {
+            feeds
+                .entry(meta.author)
+                .or_default()
+                .push((meta.sequence, meta.timestamp, previous));
+        }
+    }
+    for msg in msgs {
+        let meta: TimestampMeta = serde_json::from_slice(msg).map_err(|e| {
+            format!(
+                "found invalid message: failed to parse message timestamp: {}: {}",
+                e,
+                message::as_str(msg)
+            )
+        })?;
+        if let (Some(now), Some(max_skew)) = (policy.now, policy.max_skew) {
+            if meta.timestamp > now + max_skew {
+                return Err(format!(
+                    "found invalid message: timestamp too far in the future: {} is more than {} ms ahead of {}: {}",
+                    meta.timestamp,
+                    max_skew,
+                    now,
+                    message::as_str(msg)
+                ));
+            }
+        }
+        feeds
+            .entry(meta.author)
+            .or_default()
+            .push((meta.sequence, meta.timestamp, msg));
+    }
+
+    let mut warnings = Vec::new();
+    let monotonic = match policy.monotonic {
+        Some(monotonic) => monotonic,
+        None => return Ok(None),
+    };
+    for (author, mut feed) in feeds {
+        feed.sort_by_key(|(sequence, _, _)| *sequence);
+        for pair in feed.windows(2) {
+            let (prev_sequence, prev_timestamp, _) = pair[0];
+            let (sequence, timestamp, msg) = pair[1];
+            if timestamp < prev_timestamp {
+                let violation = format!(
+                    "timestamp not monotonic: {} of {} at sequence {} is earlier than {} at sequence {}",
+                    timestamp, author, sequence, prev_timestamp, prev_sequence
+                );
+                if monotonic == Monotonic::Strict {
+                    return Err(format!(
+                        "found invalid message: {}: {}",
+                        violation,
+                        message::as_str(msg)
+                    ));
+                }
+                warnings.push(violation);
+            }
+        }
+    }
+
+    Ok(Some(warnings))
+}
@@ -0,0 +1,32 @@
+// SPDX-FileCopyrightText: 2021 Andrew 'glyph' Reid
+//
+// SPDX-License-Identifier: Unlicense
+
+// a pool of entries of a Bamboo log (hex encoded) with sequence numbers 1, 4, 13 and 7, along
+// with their payloads and keys; entry 13 is reached from entry 1 by lipmaa links through
+// entry 4, while the entries linked to by entry 7 are missing
+//
+// NOTE: these vectors were generated by a standalone script following the format
+// specification, not by the reference implementation (bamboo-rs); vectors produced by
+// bamboo-rs are still to be added to cross-check the encoding
+export default {
+  entries: [
+    "00e87a99d764152fea450e3f0e78f5b84d909861cbbc64c3094e7604c85e358dff00011000403a94c503b24f470e9a14ce56b00aedaba5309bb93da648ebdf8baa46fe0221249a9d49fa233401fb046ee84a07964e58e70514082bbcff3ce27f8dcbe6b3db616b8a5235f3fe14ede2a3b6548c6edf3fc20684d1771cbd399b212a3ca50d3fab791bac301c665f54fd8d2b9e1ed8d9c27ed9d9e8c78a1ef86bcad136853bbc00",
+    "00e87a99d764152fea450e3f0e78f5b84d909861cbbc64c3094e7604c85e358dff0004004098f79297ad8e2656eef3cc922e1e7847aae62669390bd91acde441f926b127c6de5273ec263e1ccaa8d3d863326fd2a050c78daafef2d09f18b603702a41c864004091ba04de5c0f45eacd29053403c36824d6f4f1895ec2319b336538d257ce1a928b8cde5780458572724866018dd3edc723b04413277eeabf6f9c3d98d189554010004088694129278a0420c6e339212fd79963f1391aff68dbf36555e8a14cede3c4e3175721c1f7ac3fcf2e3d7b8f3148e6e3cd4d571c7c33fd0efd86d1363a8b7e574010d28fb5863fd4468c248e30e95c67fa3f8d76cd6e2266eae95dc3ee6d2175076c858ec4f3d5633363fb3bcbc83fe0bcf50c2d9b899f3fcee08e1a3f47f707",
+    "00e87a99d764152fea450e3f0e78f5b84d909861cbbc64c3094e7604c85e358dff000d0040d2166b6e054fdc512713f12b1beed4bb5b232e9a320a0032eec3fc9b259743f4461650091feeca9ffdeb77035992bd29fccf4c0b6fdfbffe5ff7571cbb91b23f00409a6dbae5ccc37fdf97cdeb94e4a5238bb8e905ecd9c47703ab3abe202c47de433775f1de37aa5c4a840ede6fb39f490fb27957b481be449a431663130adeddb2110040f84d8fede869a9249ac02c9abdd35bf7e30f9bbb7cb78904b15671cbcf661bd4a04e9c460f370f86aa0035ac45a04f892c6b8ed774861eec283beef81e2749b9175f99d25b7174c5abdc523b9e31e6f0423fb60000f9cce22b3c15979123bdd92900fdc305930a4cdd43ed3217fa79c67280b92daade47932b8a2560eb47270a",
+    "00e87a99d764152fea450e3f0e78f5b84d909861cbbc64c3094e7604c85e358dff000700409936fec68e7082145f6e2fa45a2a3b6dbb1583e9a24b913be647b61caf8b7e7bb96ad2dd42158a0ba515d5af83b180dda844d424893009613c64b15d8b09596f10004098a43a032215bd7db33c1370ec8d6ec064b98824f556c635fe737003a33748af86dee2cb5d0e540ed2b186481a59762c3df89fe0636d00ca3fec343caa594f6400b2f75a56534c8b7c54727fadedf922c817f56c6860549330ae0e7b89fc8678b2c4df5ab2d5727b52f5ab775213907773ad668715c17db2a0e48aa902114a0f",
+  ],
+  payloads: [
+    "62616d626f6f207061796c6f61642031",
+    "62616d626f6f207061796c6f61642034",
+    "62616d626f6f207061796c6f6164203133",
+    "62616d626f6f207061796c6f61642037",
+  ],
+  keys: [
+    "004098f79297ad8e2656eef3cc922e1e7847aae62669390bd91acde441f926b127c6de5273ec263e1ccaa8d3d863326fd2a050c78daafef2d09f18b603702a41c864",
+    "0040d2166b6e054fdc512713f12b1beed4bb5b232e9a320a0032eec3fc9b259743f4461650091feeca9ffdeb77035992bd29fccf4c0b6fdfbffe5ff7571cbb91b23f",
+    "0040a089da4279af6270427328fca78381a353ecef6f19e7dfe438c34988019e4520c2323de5ffdb445d509f85c410858315853165c849eabd799ad76d046271fa3b",
+    "0040f491a7fffb1eb3b8fb8b719744becbe56df072ebd9c9f957ade0479fd90ee4e10bcb3175601f9362fe80d85db64add06089745f0b6fd813c80ca0a945ab2fa71",
+  ],
+  anchored: [true, true, true, false],
+};
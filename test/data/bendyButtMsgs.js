@@ -0,0 +1,41 @@
+// SPDX-FileCopyrightText: 2021 Andrew 'glyph' Reid
+//
+// SPDX-License-Identifier: Unlicense
+
+// the first two messages of a Bendy Butt metafeed (base64 encoded), adding an existing main
+// feed and a derived index feed, along with their keys and the message of the main feed which
+// announces the metafeed in turn
+//
+// NOTE: these vectors were generated by a standalone script following the format
+// specification, not by the reference implementation (ssb-bendy-butt); vectors produced by
+// ssb-bendy-butt are still to be added to cross-check the encoding
+export default {
+  author: "@WPw2jU899a1HIGR7eDFx6swyejmuQN1Kec2T9xNaQAQ=.bbfeed-v1",
+  msgs: [
+    "bGwzNDoAA1j8No1PPfWtRyBke3gxcerMMno5rkDdSnnNk/cTWkAEaTFlMjoGAmkxNjQwMDAwMDAwMDAxZWxkMTE6ZmVlZHB1cnBvc2U0Om1haW44Om1ldGFmZWVkMzQ6AANY/DaNTz31rUcgZHt4MXHqzDJ6Oa5A3Up5zZP3E1pABDc6c3ViZmVlZDM0OgAAHP6+Ubnv8S/rMMIT5oRRo/gEPRvNRy9EmtvuOXxlmyo3OnRhbmdsZXNkODptZXRhZmVlZGQ4OnByZXZpb3VzMjoGAjQ6cm9vdDI6BgJlZTQ6dHlwZTIxOm1ldGFmZWVkL2FkZC9leGlzdGluZ2U2NjoEAJR1RFilWauIdyOM3nzyJygkrj1dSGXxx4CNEKMxChS33WXFE877oUVZ/WqZTxGng815vIMhxRjHy23YdnJ5AA9lZTY2OgQAlDoKtNE6na0s6BtVDx5N9N6MKKP+cbbqxTnnsunDVD+b0c8XQFJXNpMXAPdP2BBmz7r/tfff2KoNrUf/owVQBmU=",
+    "bGwzNDoAA1j8No1PPfWtRyBke3gxcerMMno5rkDdSnnNk/cTWkAEaTJlMzQ6AQRFWx3YASZim3sCJL/MzmXgx4Bdm5tpIziL/bs59AAf62kxNjQwMDAwMDAwMDAyZWxkMTE6ZmVlZHB1cnBvc2U1OmluZGV4ODptZXRhZmVlZDM0OgADWPw2jU899a1HIGR7eDFx6swyejmuQN1Kec2T9xNaQAQ1Om5vbmNlMzI6eDd7UldXtJRCf4kBT5fXmSjzk40U61HiD7XeyYNOswQ3OnN1YmZlZWQzNDoAA+vSN54tLKeyNg09FDvCtv6wR9eyERvq02uR+Jg6KO76Nzp0YW5nbGVzZDg6bWV0YWZlZWRkODpwcmV2aW91czI6BgI0OnJvb3QyOgYCZWU0OnR5cGUyMDptZXRhZmVlZC9hZGQvZGVyaXZlZGU2NjoEAPjo5OPBTI1I9ALnHdJzczY5eh4OWCvPe8UXAC4eAuUUe5FJ+HmnSGJTZvA+QXDGeM/1yjhFUsT00MVraWXeMQxlZTY2OgQAhPJw6++EFsaUgqLVZuwxQV0mMFnJm/WqBwMLjI7XHdH5bQAI3lqazSVP+hbRKDNIpS+q3PmU/O6FsUUiI85JB2U=",
+  ],
+  keys: [
+    "%RVsd2AEmYpt7AiS/zM5l4MeAXZubaSM4i/27OfQAH+s=.bbmsg-v1",
+    "%8IAkJ0qs2q5/As6NUAOvCaDU6JT2MF9qwRQg5CfCsKU=.bbmsg-v1",
+  ],
+  mainFeed: "@HP6+Ubnv8S/rMMIT5oRRo/gEPRvNRy9EmtvuOXxlmyo=.ed25519",
+  announce: {
+    previous: null,
+    author: "@HP6+Ubnv8S/rMMIT5oRRo/gEPRvNRy9EmtvuOXxlmyo=.ed25519",
+    sequence: 1,
+    timestamp: 1640000000000,
+    hash: "sha256",
+    content: {
+      type: "metafeed/announce",
+      metafeed: "@WPw2jU899a1HIGR7eDFx6swyejmuQN1Kec2T9xNaQAQ=.bbfeed-v1",
+      tangles: {
+        metafeed: {
+          root: null,
+          previous: null,
+        },
+      },
+    },
+    signature: "BcS6I86VagHmK237zm2J0HH9sPdl7Wot9sp8AYOydZhWPfoEIa2p499nPBmtk8+ffRYvL4rjjRas8MdcgYyaCg==.sig.ed25519",
+  },
+};
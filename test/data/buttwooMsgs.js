@@ -0,0 +1,22 @@
+// SPDX-FileCopyrightText: 2021 Andrew 'glyph' Reid
+//
+// SPDX-License-Identifier: Unlicense
+
+// the first three messages of a buttwoo feed (base64 encoded), along with their keys
+//
+// NOTE: these vectors were generated by a standalone script following the format
+// specification, not by the reference implementation (ssb-buttwoo); vectors produced by
+// ssb-buttwoo are still to be added to cross-check the encoding
+export default {
+  author: "ssb:feed/buttwoo-v1/VQR-kdKuv2duFoIRWli9eMCHY_bbh8gbawEyAKel-Mk",
+  msgs: [
+    "9AyhBpQGkQIABFUEfpHSrr9nbhaCEVpYvXjAh2P224fIG2sBMgCnpfjJEQYCIgEAAABDABAA4Xndd0IRBgIJACIkAAAAiQIAWqiErQiVkzfZn2R7CIQiGQhmRKVmpIjx4FQACBk4RyuBBODCAME8rRpQw5w0ZAlFD1a8EMCtwQMxyG9nPytH/vEiHHIk4wYbKGu1duB0e/Gz/0XuRaFHU4Z6KDe/SKJumwOhApUCIHR5cGUgcG9zdCB0ZXh0iAFidXR0d29vIG1lc3NhZ2UgMQ==",
+    "/A6pCJwIkQIABFUEfpHSrr9nbhaCEVpYvXjAh2P224fIG2sBMgCnpfjJEQYCIgIAAABDACAA4Xndd0KRAgEFjuQp4lGZWTfIThp8CmceKO8ciYiefMl/mpVrPLxsTVkJACIkAAAAiQIAgL3N29bmg8OLNIx7Ej1U07J1K1MKKfNERH7keyQ+BF+BBCpdetUDD0VPwaDXtuPOe9sw3gMy9gVL7AyFxMFBl3rj2KtKKgFySwgwc2Kpm/E8vnfB3lTp8XpCCvoQeC9LPAShApUCIHR5cGUgcG9zdCB0ZXh0iAFidXR0d29vIG1lc3NhZ2UgMg==",
+    "/A6pCJwIkQIABFUEfpHSrr9nbhaCEVpYvXjAh2P224fIG2sBMgCnpfjJEQYCIgMAAABDADAA4Xndd0KRAgEFwPlqrdfgzCic0X7O/Fc9jBWsVb21YCzLfhY4tX2t9dYJACIkAAAAiQIADjW7GdI4nkboQRYnGmgZZvYAYVbvMae33CrlLkvlIoyBBLDP5GThXnufTVbRgEtTsjiQ1CGF3mUAFNfPlVQRrCHfWfidOqXbpp81V9LVFERy2c3tAEPEFrpx2si5xQ6WQAShApUCIHR5cGUgcG9zdCB0ZXh0iAFidXR0d29vIG1lc3NhZ2UgMw==",
+  ],
+  keys: [
+    "ssb:message/buttwoo-v1/juQp4lGZWTfIThp8CmceKO8ciYiefMl_mpVrPLxsTVk",
+    "ssb:message/buttwoo-v1/wPlqrdfgzCic0X7O_Fc9jBWsVb21YCzLfhY4tX2t9dY",
+    "ssb:message/buttwoo-v1/4Jvel5IhdGX-_V-7rnR5cuV6D4K3xUyaGCfzH9gkrK0",
+  ],
+};
@@ -0,0 +1,22 @@
+// SPDX-FileCopyrightText: 2021 Andrew 'glyph' Reid
+//
+// SPDX-License-Identifier: Unlicense
+
+// the first three events of a Gabby Grove feed (base64 encoded), along with their keys
+//
+// NOTE: these vectors were generated by a standalone script following the format
+// specification, not by the reference implementation (go-ssb (gabbygrove)); vectors produced by
+// go-ssb (gabbygrove) are still to be added to cross-check the encoding
+export default {
+  author: "@j423R0x1vYYvfc/AWOnGiV6a2EZ1yc/nJStPfDSmF9w=.ggfeed-v1",
+  msgs: [
+    "g1hYhfbZBBpYIQGPjbdHTHW9hi99z8BY6caJXprYRnXJz+clK098NKYX3AEaYcBqAYPZBBpYIQO9EkThxvdL5QlXXxOr4TP782IvCYJFb79p3aE7YHx4vRgsAVhAZQbeWcb809NQK2AshBbhj3ma4TXZ+/L144vSlP4k2kHAImp8FcRyV/aIMj+GMItitZtjv6qbmGnHWRuB5gmND1gseyJ0eXBlIjoicG9zdCIsInRleHQiOiJnYWJieSBncm92ZSBldmVudCAxIn0=",
+    "g1h9hdkEGlghAiK/tyU+QU72ZXFiFTpGjsktfp3ndugA85V3U9kjyulZ2QQaWCEBj423R0x1vYYvfc/AWOnGiV6a2EZ1yc/nJStPfDSmF9wCGmHAagKD2QQaWCEDzQJ5PGx2yU6BthoQo16fY0iLwMStNjd3y+3DKTZhHNEYLAFYQC/5DpvERH7W6PcoKZDlBFRSosOiFm/illbr32RTOHVEPgmKjr3F6NG5ZLNLjeb4QQZcs/hbup/pb1qt8V32UwBYLHsidHlwZSI6InBvc3QiLCJ0ZXh0IjoiZ2FiYnkgZ3JvdmUgZXZlbnQgMiJ9",
+    "g1h9hdkEGlghAh7wFNyJtMlATHHPvcwyXyQ917r0Bz3VE+s3Aqnrl1Lz2QQaWCEBj423R0x1vYYvfc/AWOnGiV6a2EZ1yc/nJStPfDSmF9wDGmHAagOD2QQaWCED7dQvETNa+6Q9Txu/D7h+HKLZRR7C5WIuAcJjIJRRQ3YYLAFYQIv82bOyA4jKuucx4ci5SclwFbPuHBaQrNXik9EDDgX86ACPxs9tAK0yXrKliwXV1HjuQ+UbH9x/+PEMMVr/DgBYLHsidHlwZSI6InBvc3QiLCJ0ZXh0IjoiZ2FiYnkgZ3JvdmUgZXZlbnQgMyJ9",
+  ],
+  keys: [
+    "%Ir+3JT5BTvZlcWIVOkaOyS1+ned26ADzlXdT2SPK6Vk=.ggmsg-v1",
+    "%HvAU3Im0yUBMcc+9zDJfJD3XuvQHPdUT6zcCqeuXUvM=.ggmsg-v1",
+    "%wS0OnRWHRF8uorI6aOnquCwKKOslNNQYSWpFJ76Zws0=.ggmsg-v1",
+  ],
+};
@@ -11,6 +11,10 @@ import validMsg from "./data/valid.js";
 import validMsgKey from "./data/validKey.js";
 import validHmacMsg from "./data/validHmac.js";
 import validHmacMsgKey from "./data/validHmacKey.js";
+import bendyButtMsgs from "./data/bendyButtMsgs.js";
+import buttwooMsgs from "./data/buttwooMsgs.js";
+import gabbyGroveMsgs from "./data/gabbyGroveMsgs.js";
+import bambooEntries from "./data/bambooEntries.js";
 
 // "The buffer module from node.js, for the browser"
 //const Buffer = require('buffer/').Buffer;
@@ -59,8 +63,94 @@ const validate = {
       .validateMultiAuthorBatch(hmacKey, msgs)
       .then(([err, res]) => cb(err, res));
   },
+
+  validate(msgs, options, cb) {
+    wrapped.validate(msgs, options).then(([err, res]) => cb(err, res));
+  },
+
+  validateBendyButtBatch(hmacKey, msgs, previous, cb) {
+    wrapped
+      .validateBendyButtBatch(hmacKey, msgs, previous)
+      .then(([err, res]) => cb(err, res));
+  },
+
+  validateButtwooBatch(hmacKey, msgs, previous, cb) {
+    wrapped
+      .validateButtwooBatch(hmacKey, msgs, previous)
+      .then(([err, res]) => cb(err, res));
+  },
+
+  validateGabbyGroveBatch(hmacKey, msgs, previous, cb) {
+    wrapped
+      .validateGabbyGroveBatch(hmacKey, msgs, previous)
+      .then(([err, res]) => cb(err, res));
+  },
+
+  validateBambooBatch(entries, payloads, cb) {
+    wrapped
+      .validateBambooBatch(entries, payloads)
+      .then(([err, res]) => cb(err, res));
+  },
+
+  validateMetafeedTree(hmacKey, metafeedMsgs, mainFeedMsgs, cb) {
+    wrapped
+      .validateMetafeedTree(hmacKey, metafeedMsgs, mainFeedMsgs)
+      .then(([err, res]) => cb(err, res));
+  },
+
+  detectForks(hmacKey, msgs, knownTips, cb) {
+    wrapped
+      .detectForks(hmacKey, msgs, knownTips)
+      .then(([err, res]) => cb(err, res));
+  },
+
+  validateWithState(hmacKey, msgs, cb) {
+    wrapped.validateWithState(hmacKey, msgs).then(([err, res]) => cb(err, res));
+  },
+
+  exportState(cb) {
+    wrapped.exportState().then(([err, res]) => cb(err, res));
+  },
+
+  importState(snapshot, cb) {
+    wrapped.importState(snapshot).then(([err, res]) => cb(err, res));
+  },
+
+  validateBuffered(hmacKey, msgs, cb) {
+    wrapped.validateBuffered(hmacKey, msgs).then(([err, res]) => cb(err, res));
+  },
+
+  seedBufferFromState(cb) {
+    wrapped.seedBufferFromState().then(([err, res]) => cb(err, res));
+  },
 };
 
+const fromBase64 = (str) => Uint8Array.from(atob(str), (c) => c.charCodeAt(0));
+const fromHex = (str) =>
+  Uint8Array.from(str.match(/../g), (byte) => parseInt(byte, 16));
+
+// fixed orderings of a batch of (up to) ten messages: reversed, each successor before its
+// predecessor, and a mix of both
+const permutations = [
+  [9, 8, 7, 6, 5, 4, 3, 2, 1, 0],
+  [1, 0, 3, 2, 5, 4, 7, 6, 9, 8],
+  [6, 2, 9, 0, 4, 8, 1, 7, 3, 5],
+];
+
+// reorder a batch by a permutation, leaving out the positions beyond the end of the batch
+const permute = (msgs, order) =>
+  order.filter((idx) => idx < msgs.length).map((idx) => msgs[idx]);
+
+// run `check(order, cb)` for each permutation in turn, where `cb` is passed whether the check
+// passed
+function forEachPermutation(check, done) {
+  const next = (i) =>
+    i === permutations.length
+      ? done()
+      : check(permutations[i], (passed) => (passed ? next(i + 1) : done("failed")));
+  next(0);
+}
+
 const hmacKey1 = null;
 const hmacKey2 = 'CbwuwYXmZgN7ZSuycCXoKGOTU1dGwBex+paeA2kr37U=';
 
@@ -94,6 +184,31 @@ describe("test: ", function () {
     });
   });
 
+  it("batch validation beyond the batch size limit", function (done) {
+    const msgs = singleAuthorMsgs.map((msg) => msg.value);
+    validate.validate(msgs, { hmacKey: hmacKey1, limits: { maxMessages: 5 } }, (err, res) => {
+      if (err && err.includes("limit exceeded: batch has 10 messages")) done();
+      else done("failed");
+    });
+  });
+
+  it("batch validation beyond the message size limit", function (done) {
+    const msgs = singleAuthorMsgs.map((msg) => msg.value);
+    validate.validate(msgs, { hmacKey: hmacKey1, limits: { maxMessageBytes: 64 } }, (err, res) => {
+      if (err && err.includes("limit exceeded: message at position 0")) done();
+      else done("failed");
+    });
+  });
+
+  it("batch validation beyond the default message size limit", function (done) {
+    const msgs = JSON.parse(JSON.stringify(singleAuthorMsgs.map((msg) => msg.value)));
+    msgs[1].content.text = "x".repeat(70000);
+    validate.validateOOOBatch(hmacKey1, msgs, (err, res) => {
+      if (err && err.includes("limit exceeded: message at position 1")) done();
+      else done("failed");
+    });
+  });
+
   it("verification of single message signature (valid)", function (done) {
     let validMsgClone = JSON.parse(JSON.stringify(validMsg));
     let msgs = [validMsgClone.value];
@@ -137,6 +252,48 @@ describe("test: ", function () {
     });
   });
 
+  it("verification of single message signature with hmac (invalid string)", function (done) {
+    let msgs = [validHmacMsg];
+    validate.verifySignatures("not a base64 key", msgs, (err, res) => {
+      if (err && err.includes("hmac key invalid: string must be base64 encoded")) done();
+      else done("failed");
+    });
+  });
+
+  it("validation of a single message with a list of hmac keys (invalid)", function (done) {
+    // `validateSingle` takes a single key only
+    validate.validateSingle([null, hmacKey2], validMsg.value, null, (err, res) => {
+      if (err && err.includes("hmac key invalid: must be a single key")) done();
+      else done("failed");
+    });
+  });
+
+  it("verification of message signatures with a list of hmac keys", function (done) {
+    let msgs = [validMsg.value, validHmacMsg];
+    // try each message without an hmac key first, then with `hmacKey2`
+    validate.verifySignatures([null, hmacKey2], msgs, (err, res) => {
+      if (err) return done("failed");
+      const isEqual =
+        JSON.stringify([...validMsgKey, ...validHmacMsgKey]) ===
+        JSON.stringify(res.keys);
+      if (isEqual && JSON.stringify(res.matchedKeys) === "[0,1]") done();
+      else done("failed");
+    });
+  });
+
+  it("verification of message signatures with per-author hmac keys", function (done) {
+    let msgs = [validMsg.value, validHmacMsg];
+    // only the author of `validHmacMsg` uses `hmacKey2`
+    const hmacKeys = { authors: { [validHmacMsg.author]: hmacKey2 }, default: null };
+    validate.verifySignatures(hmacKeys, msgs, (err, res) => {
+      const isEqual =
+        JSON.stringify([...validMsgKey, ...validHmacMsgKey]) ===
+        JSON.stringify(res);
+      if (!err && isEqual) done();
+      else done("failed");
+    });
+  });
+
   it("validation of first message (`seq` == 1) without `previous`", function (done) {
     validate.validateSingle(
       hmacKey1,
@@ -218,6 +375,39 @@ describe("test: ", function () {
     });
   });
 
+  it("batch validation of partial feed with a `previous` reference", function (done) {
+    const msgs = singleAuthorMsgs.map((msg) => msg.value);
+    const mutMsgs = [...msgs];
+    // reference the first msg by key, sequence and author
+    const first = mutMsgs.shift();
+    const previous = {
+      key: singleAuthorMsgsKeys[0],
+      sequence: first.sequence,
+      author: first.author,
+    };
+    validate.validateBatch(hmacKey1, mutMsgs, previous, (err, res) => {
+      const isEqual =
+        JSON.stringify(singleAuthorMsgsKeys.slice(1, 10)) ===
+        JSON.stringify(res);
+      if (!err && isEqual) done();
+      else done("failed");
+    });
+  });
+
+  it("batch validation of partial feed from a trusted checkpoint", function (done) {
+    const msgs = singleAuthorMsgs.map((msg) => msg.value);
+    // validate the last four messages only
+    const tail = msgs.slice(6);
+    validate.validate(tail, { hmacKey: hmacKey1, checkpoint: true }, (err, res) => {
+      if (err) return done("failed");
+      const isEqual =
+        JSON.stringify(singleAuthorMsgsKeys.slice(6)) ===
+        JSON.stringify(res.keys);
+      if (isEqual && res.anchoredAt === 7) done();
+      else done("failed");
+    });
+  });
+
   it("batch validation of partial feed without `previous`", function (done) {
     const msgs = singleAuthorMsgs.map((msg) => msg.value);
     const mutMsgs = [...msgs];
@@ -231,6 +421,37 @@ describe("test: ", function () {
     });
   });
 
+  it("batch validation of reordered feed with auto-sort", function (done) {
+    const msgs = singleAuthorMsgs.map((msg) => msg.value);
+    const options = { hmacKey: hmacKey1, autoSort: true };
+    forEachPermutation((order, cb) => {
+      validate.validate(permute(msgs, order), options, (err, res) => {
+        // keys are returned in the order of the input
+        const keys = permute(singleAuthorMsgsKeys, order);
+        cb(!err && JSON.stringify(keys) === JSON.stringify(res));
+      });
+    }, done);
+  });
+
+  it("batch validation of feed with timestamps ahead of `now`", function (done) {
+    const msgs = singleAuthorMsgs.map((msg) => msg.value);
+    // pretend the current time is the unix epoch, so every timestamp is in the future
+    const timestampPolicy = { now: 0, maxSkew: 60000 };
+    validate.validate(msgs, { hmacKey: hmacKey1, timestampPolicy }, (err, res) => {
+      if (err && err.includes("timestamp too far in the future")) done();
+      else done("failed");
+    });
+  });
+
+  it("batch validation with a negative `maxSkew`", function (done) {
+    const msgs = singleAuthorMsgs.map((msg) => msg.value);
+    const timestampPolicy = { now: Date.now(), maxSkew: -1 };
+    validate.validate(msgs, { hmacKey: hmacKey1, timestampPolicy }, (err, res) => {
+      if (err && err.includes("`maxSkew` must be a non-negative number")) done();
+      else done("failed");
+    });
+  });
+
   it("batch validation of out-of-order messages", function (done) {
     const msgs = singleAuthorMsgs.map((msg) => msg.value);
     const oooMsgs = [...msgs];
@@ -242,6 +463,18 @@ describe("test: ", function () {
     });
   });
 
+  it("batch validation of out-of-order messages with link checks", function (done) {
+    const msgs = singleAuthorMsgs.map((msg) => msg.value);
+    // drop a message to leave a gap in the batch
+    const gapMsgs = msgs.filter((msg) => msg.sequence !== 5);
+    const options = { hmacKey: hmacKey1, ordering: "outOfOrder", checkLinks: true };
+    forEachPermutation((order, cb) => {
+      validate.validate(permute(gapMsgs, order), options, (err, res) => {
+        cb(!err && res.length === 9);
+      });
+    }, done);
+  });
+
   it("batch validation of out-of-order multi-author messages", function (done) {
     const msgs = multiAuthorMsgs.map((msg) => msg.value);
     // shuffle the messages (generate out-of-order state)
@@ -252,4 +485,402 @@ describe("test: ", function () {
       else done("failed");
     });
   });
+
+  it("batch validation of out-of-order messages with gap report", function (done) {
+    const msgs = singleAuthorMsgs.map((msg) => msg.value);
+    // drop messages 4 to 6 to leave a gap in the batch
+    const gapMsgs = msgs.filter((msg) => msg.sequence < 4 || msg.sequence > 6);
+    const options = { hmacKey: hmacKey1, ordering: "outOfOrder", reportGaps: true };
+    forEachPermutation((order, cb) => {
+      validate.validate(permute(gapMsgs, order), options, (err, res) => {
+        if (err) return cb(false);
+        const [feed] = res.feeds;
+        cb(
+          res.keys.length === 7 &&
+            feed.minSequence === 1 &&
+            feed.maxSequence === 10 &&
+            JSON.stringify(feed.missing) === JSON.stringify([[4, 6]])
+        );
+      });
+    }, done);
+  });
+
+  it("batch validation of multi-author messages with duplicates", function (done) {
+    const msgs = multiAuthorMsgs.map((msg) => msg.value);
+    // repeat the first two messages at the end of the batch
+    const dupMsgs = [...msgs, msgs[0], msgs[1]];
+    const options = { hmacKey: hmacKey1, ordering: "multiAuthor", dedupe: true };
+    validate.validate(dupMsgs, options, (err, res) => {
+      if (err) return done("failed");
+      const isEqual =
+        JSON.stringify(multiAuthorMsgs.map((msg) => msg.key)) ===
+        JSON.stringify(res.keys);
+      if (isEqual && JSON.stringify(res.duplicates) === "[10,11]") done();
+      else done("failed");
+    });
+  });
+
+  it("batch validation of multi-author messages with per-author chains", function (done) {
+    const msgs = multiAuthorMsgs.map((msg) => msg.value);
+    const keys = multiAuthorMsgs.map((msg) => msg.key);
+    const options = { hmacKey: hmacKey1, ordering: "multiAuthor", chainPerAuthor: true };
+    forEachPermutation((order, cb) => {
+      validate.validate(permute(msgs, order), options, (err, res) => {
+        // keys are returned in the order of the input
+        cb(!err && JSON.stringify(permute(keys, order)) === JSON.stringify(res));
+      });
+    }, done);
+  });
+
+  it("batch validation of multi-author messages skipping a blocked author", function (done) {
+    const msgs = multiAuthorMsgs.map((msg) => msg.value);
+    const blocked = msgs[0].author;
+    const authorFilter = { block: [blocked], skip: true };
+    const skipped = msgs
+      .map((msg, position) => (msg.author === blocked ? position : -1))
+      .filter((position) => position !== -1);
+    const options = { hmacKey: hmacKey1, ordering: "multiAuthor", authorFilter };
+    validate.validate(msgs, options, (err, res) => {
+      if (err) return done("failed");
+      const isEqual = JSON.stringify(skipped) === JSON.stringify(res.skipped);
+      if (isEqual && res.keys.length === msgs.length - skipped.length) done();
+      else done("failed");
+    });
+  });
+
+  it("validation of every message with `failFast: false`", function (done) {
+    const msgs = JSON.parse(JSON.stringify(singleAuthorMsgs.map((msg) => msg.value)));
+    // invalidate the signature of the last message
+    msgs[9].timestamp += 1;
+    const options = { hmacKey: hmacKey1, failFast: false };
+    validate.validate(msgs, options, (err, res) => {
+      if (
+        !err &&
+        res.keys.length === 10 &&
+        JSON.stringify(res.keys.slice(0, 9)) ===
+          JSON.stringify(singleAuthorMsgsKeys.slice(0, 9)) &&
+        res.invalid.length === 1 &&
+        res.invalid[0].position === 9
+      )
+        done();
+      else done("failed");
+    });
+  });
+
+  it("validation with options not applying to the ordering", function (done) {
+    const msgs = singleAuthorMsgs.map((msg) => msg.value);
+    const options = { hmacKey: hmacKey1, ordering: "outOfOrder", autoSort: true };
+    validate.validate(msgs, options, (err, res) => {
+      if (err && err.includes("`autoSort` does not apply")) done();
+      else done("failed");
+    });
+  });
+
+  it("validation with the ordering detected per feed", function (done) {
+    const msgs = singleAuthorMsgs.map((msg) => msg.value);
+    // leave a gap in the feed, so it can only be validated out-of-order
+    const sparseMsgs = [...msgs.slice(0, 4), ...msgs.slice(5)];
+    const options = { hmacKey: hmacKey1, ordering: "auto" };
+    forEachPermutation((order, cb) => {
+      validate.validate(permute(sparseMsgs, order), options, (err, res) => {
+        cb(
+          !err &&
+            res.keys.length === 9 &&
+            res.groups.length === 1 &&
+            res.groups[0].mode === "outOfOrder" &&
+            res.groups[0].minSequence === 1 &&
+            res.groups[0].maxSequence === 10
+        );
+      });
+    }, done);
+  });
+
+  it("validation of a reordered feed with the ordering detected", function (done) {
+    const msgs = singleAuthorMsgs.map((msg) => msg.value);
+    const options = { hmacKey: hmacKey1, ordering: "auto" };
+    // a contiguous run in any order is still validated as a hash chain
+    forEachPermutation((order, cb) => {
+      validate.validate(permute(msgs, order), options, (err, res) => {
+        cb(!err && res.groups.length === 1 && res.groups[0].mode === "fullChain");
+      });
+    }, done);
+  });
+
+  it("validation with selected rules only", function (done) {
+    const msgs = JSON.parse(JSON.stringify(singleAuthorMsgs.map((msg) => msg.value)));
+    // invalidate the signature of the last message, which is not checked
+    msgs[9].content.text = "edited";
+    const options = { hmacKey: hmacKey1, rules: { signature: false } };
+    validate.validate(msgs, options, (err, res) => {
+      const isEqual =
+        JSON.stringify(singleAuthorMsgsKeys.slice(0, 9)) ===
+        JSON.stringify(res.slice(0, 9));
+      if (!err && res.length === 10 && isEqual) done();
+      else done("failed");
+    });
+  });
+
+  it("validation with a rule disabled still applies the others (invalid)", function (done) {
+    const msgs = JSON.parse(JSON.stringify(singleAuthorMsgs.map((msg) => msg.value)));
+    // break the link of a message, which is still checked without the `hash` rule
+    msgs[5].previous = msgs[3].previous;
+    const options = { hmacKey: hmacKey1, rules: { signature: false, hash: false } };
+    validate.validate(msgs, options, (err, res) => {
+      if (err && err.includes("found invalid message")) done();
+      else done("failed");
+    });
+  });
+
+  it("out-of-order validation with selected rules only", function (done) {
+    const msgs = JSON.parse(JSON.stringify(singleAuthorMsgs.map((msg) => msg.value)));
+    msgs.reverse();
+    // invalidate the signature of a message, which is not checked
+    msgs[0].content.text = "edited";
+    const options = {
+      hmacKey: hmacKey1,
+      ordering: "outOfOrder",
+      rules: { signature: false },
+    };
+    validate.validate(msgs, options, (err, res) => {
+      if (!err && res.length === 10) done();
+      else done("failed");
+    });
+  });
+
+  it("batch validation of a Bendy Butt feed", function (done) {
+    const msgs = bendyButtMsgs.msgs.map(fromBase64);
+    validate.validateBendyButtBatch(hmacKey1, msgs, null, (err, res) => {
+      const isEqual = JSON.stringify(bendyButtMsgs.keys) === JSON.stringify(res);
+      if (!err && isEqual) done();
+      else done("failed");
+    });
+  });
+
+  it("batch validation of a buttwoo feed", function (done) {
+    const msgs = buttwooMsgs.msgs.map(fromBase64);
+    validate.validateButtwooBatch(hmacKey1, msgs, null, (err, res) => {
+      const isEqual = JSON.stringify(buttwooMsgs.keys) === JSON.stringify(res);
+      if (!err && isEqual) done();
+      else done("failed");
+    });
+  });
+
+  it("batch validation of a Gabby Grove feed", function (done) {
+    const msgs = gabbyGroveMsgs.msgs.map(fromBase64);
+    validate.validateGabbyGroveBatch(hmacKey1, msgs, null, (err, res) => {
+      const isEqual =
+        JSON.stringify(gabbyGroveMsgs.keys) === JSON.stringify(res);
+      if (!err && isEqual) done();
+      else done("failed");
+    });
+  });
+
+  it("Bendy Butt message nested too deeply (invalid)", function (done) {
+    const nested = new TextEncoder().encode("l".repeat(33) + "e".repeat(33));
+    validate.validateBendyButtBatch(hmacKey1, [nested], null, (err, res) => {
+      if (err && err.includes("nested more than 32 levels deep")) done();
+      else done("failed");
+    });
+  });
+
+  it("Gabby Grove message beyond the size limit (invalid)", function (done) {
+    const oversized = new Uint8Array(16385);
+    validate.validateGabbyGroveBatch(hmacKey1, [oversized], null, (err, res) => {
+      if (err && err.includes("exceeds the maximum of 16384")) done();
+      else done("failed");
+    });
+  });
+
+  it("multi-author batch validation of mixed feed formats", function (done) {
+    const msgs = [
+      singleAuthorMsgs[0].value,
+      ...bendyButtMsgs.msgs.map(fromBase64),
+      ...gabbyGroveMsgs.msgs.map(fromBase64),
+    ];
+    const expected = [
+      singleAuthorMsgsKeys[0],
+      ...bendyButtMsgs.keys,
+      ...gabbyGroveMsgs.keys,
+    ];
+    validate.validateMultiAuthorBatch(hmacKey1, msgs, (err, res) => {
+      const isEqual = JSON.stringify(expected) === JSON.stringify(res);
+      if (!err && isEqual) done();
+      else done("failed");
+    });
+  });
+
+  it("partial verification of a pool of Bamboo entries", function (done) {
+    const entries = bambooEntries.entries.map(fromHex);
+    const payloads = bambooEntries.payloads.map(fromHex);
+    validate.validateBambooBatch(entries, payloads, (err, res) => {
+      const isEqual =
+        JSON.stringify(bambooEntries.keys) === JSON.stringify(res.keys) &&
+        JSON.stringify(bambooEntries.anchored) === JSON.stringify(res.anchored);
+      if (!err && isEqual) done();
+      else done("failed");
+    });
+  });
+
+  it("pool of Bamboo entries with too few payloads (invalid)", function (done) {
+    const entries = bambooEntries.entries.map(fromHex);
+    const payloads = bambooEntries.payloads.slice(1).map(fromHex);
+    validate.validateBambooBatch(entries, payloads, (err, res) => {
+      if (err && err.includes("payloads invalid: 3 payloads given for 4 entries")) done();
+      else done("failed");
+    });
+  });
+
+  it("Bamboo entry with a huge sequence number (invalid)", function (done) {
+    // replace the sequence number of the first entry (after the end of feed flag, the author
+    // and the log id) with the largest varu64
+    const entry = fromHex(bambooEntries.entries[0]);
+    const huge = new Uint8Array([...entry.slice(0, 34), ...new Array(9).fill(0xff), ...entry.slice(35)]);
+    validate.validateBambooBatch([huge], null, (err, res) => {
+      if (err && err.includes("sequence number must be at most")) done();
+      else done("failed");
+    });
+  });
+
+  it("metafeed tree consistency", function (done) {
+    const msgs = bendyButtMsgs.msgs.map(fromBase64);
+    const mainFeedMsgs = [bendyButtMsgs.announce];
+    validate.validateMetafeedTree(hmacKey1, msgs, mainFeedMsgs, (err, res) => {
+      if (err || res.roots.length !== 1) return done("failed");
+      const [root] = res.roots;
+      const isConsistent =
+        root.id === bendyButtMsgs.author &&
+        JSON.stringify(root.announcedBy) === JSON.stringify([bendyButtMsgs.mainFeed]) &&
+        root.subfeeds.length === 2 &&
+        res.inconsistencies.length === 0;
+      if (isConsistent) done();
+      else done("failed");
+    });
+  });
+
+  it("metafeed tree with a missing message", function (done) {
+    const msgs = bendyButtMsgs.msgs.slice(1).map(fromBase64);
+    validate.validateMetafeedTree(hmacKey1, msgs, [], (err, res) => {
+      if (err) return done("failed");
+      const isReported = res.inconsistencies.some(
+        ({ feed, reason }) =>
+          feed === bendyButtMsgs.author && reason === "messages 1 to 1 were not given"
+      );
+      if (isReported) done();
+      else done("failed");
+    });
+  });
+
+  it("stateful validation resumed from a snapshot", function (done) {
+    const msgs = singleAuthorMsgs.map((msg) => msg.value);
+    validate.validateWithState(hmacKey1, msgs.slice(0, 5), (err, res) => {
+      if (err) return done("failed");
+      validate.exportState((err, snapshot) => {
+        if (err) return done("failed");
+        validate.importState(snapshot, (err, count) => {
+          if (err || count !== 1) return done("failed");
+          // the remaining messages are validated against the restored feed tip
+          validate.validateWithState(hmacKey1, msgs.slice(5), (err, res) => {
+            const isEqual =
+              JSON.stringify(singleAuthorMsgsKeys.slice(5)) ===
+              JSON.stringify(res);
+            if (!err && isEqual) done();
+            else done("failed");
+          });
+        });
+      });
+    });
+  });
+
+  it("buffered validation releases messages once the predecessor arrives", function (done) {
+    const msgs = singleAuthorMsgs.map((msg) => msg.value);
+    // withhold the second message
+    const withheld = msgs.splice(1, 1);
+    validate.validateBuffered(hmacKey1, msgs, (err, res) => {
+      if (err || res.validated.length !== 1 || res.pending.length !== 8)
+        return done("failed");
+      validate.validateBuffered(hmacKey1, withheld, (err, res) => {
+        const isEqual =
+          JSON.stringify(singleAuthorMsgsKeys.slice(1)) ===
+          JSON.stringify(res.validated);
+        if (!err && isEqual && res.pending.length === 0) done();
+        else done("failed");
+      });
+    });
+  });
+
+  it("buffered validation seeded from the feed state", function (done) {
+    const author = "@58u/J9+5bOXeYRDCYQ9cJ7kklghIpQFPBYxlhKq1/qs=.ed25519";
+    const msgs = multiAuthorMsgs
+      .map((msg) => msg.value)
+      .filter((msg) => msg.author === author);
+    // the later messages wait for the first two, which the buffer never sees
+    validate.validateBuffered(hmacKey1, msgs.slice(2), (err, res) => {
+      if (err || res.pending.length !== 3) return done("failed");
+      validate.validateWithState(hmacKey1, msgs.slice(0, 2), (err, res) => {
+        if (err) return done("failed");
+        // seeding releases the messages following the tip of the state
+        validate.seedBufferFromState((err, res) => {
+          if (!err && res.validated.length === 3 && res.pending.length === 0) done();
+          else done("failed");
+        });
+      });
+    });
+  });
+
+  it("batch validation fails on a fork against the previous message", function (done) {
+    const msgs = singleAuthorMsgs.map((msg) => msg.value);
+    // a previous reference with the same position as the third message but a different key
+    const previous = {
+      author: msgs[2].author,
+      sequence: 3,
+      key: singleAuthorMsgsKeys[0],
+    };
+    validate.validateBatch(hmacKey1, msgs.slice(2), previous, (err, res) => {
+      if (err && err.includes("fork detected") && res === null) done();
+      else done("failed");
+    });
+  });
+
+  it("out-of-order validation reports forks when asked", function (done) {
+    const msgs = JSON.parse(JSON.stringify(singleAuthorMsgs.map((msg) => msg.value)));
+    // a second message at the position of the fourth, with other content (and so another key)
+    const forked = JSON.parse(JSON.stringify(msgs[3]));
+    forked.content.text = "forked";
+    msgs.push(forked);
+    const options = {
+      hmacKey: hmacKey1,
+      ordering: "outOfOrder",
+      rules: { signature: false },
+      forks: "report",
+    };
+    validate.validate(msgs, options, (err, res) => {
+      if (
+        !err &&
+        res.keys.length === 11 &&
+        res.forks.length === 1 &&
+        res.forks[0].sequence === 4 &&
+        res.forks[0].keys[0] === singleAuthorMsgsKeys[3]
+      )
+        done();
+      else done("failed");
+    });
+  });
+
+  it("fork detection against a known tip", function (done) {
+    const msgs = singleAuthorMsgs.map((msg) => msg.value);
+    // a known tip with the same position as the third message but a different key
+    const knownTips = [
+      { author: msgs[2].author, sequence: 3, key: singleAuthorMsgsKeys[0] },
+    ];
+    validate.detectForks(hmacKey1, msgs, knownTips, (err, res) => {
+      if (
+        !err &&
+        res.length === 1 &&
+        res[0].sequence === 3 &&
+        res[0].keys[1] === singleAuthorMsgsKeys[2]
+      )
+        done();
+      else done("failed");
+    });
+  });
 });
@@ -9,6 +9,25 @@ import {
   validateBatch,
   validateOOOBatch,
   validateMultiAuthorBatch,
+  validate,
+  validateBendyButtSingle,
+  validateBendyButtBatch,
+  validateBendyButtOOOBatch,
+  validateButtwooSingle,
+  validateButtwooBatch,
+  validateButtwooOOOBatch,
+  validateGabbyGroveSingle,
+  validateGabbyGroveBatch,
+  validateGabbyGroveOOOBatch,
+  validateBambooSingle,
+  validateBambooBatch,
+  validateMetafeedTree,
+  detectForks,
+  validateWithState,
+  exportState,
+  importState,
+  validateBuffered,
+  bufferPending,
 } from "./api.js";
 import * as Comlink from "comlink";
 
@@ -19,4 +38,23 @@ Comlink.expose({
   validateBatch,
   validateOOOBatch,
   validateMultiAuthorBatch,
+  validate,
+  validateBendyButtSingle,
+  validateBendyButtBatch,
+  validateBendyButtOOOBatch,
+  validateButtwooSingle,
+  validateButtwooBatch,
+  validateButtwooOOOBatch,
+  validateGabbyGroveSingle,
+  validateGabbyGroveBatch,
+  validateGabbyGroveOOOBatch,
+  validateBambooSingle,
+  validateBambooBatch,
+  validateMetafeedTree,
+  detectForks,
+  validateWithState,
+  exportState,
+  importState,
+  validateBuffered,
+  bufferPending,
 });